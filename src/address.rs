@@ -0,0 +1,82 @@
+//! Human-readable addresses derived from public keys.
+//!
+//! An address is `mc` followed by the Base58 encoding of a version byte, the first 20
+//! bytes of the SHA-256 of an ed25519 public key, and a 4-byte checksum (the start of
+//! the double SHA-256 of the preceding bytes). A mistyped address fails the checksum
+//! instead of paying a key nobody holds.
+
+use std::fmt;
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256};
+
+/// Every address starts with this.
+pub const PREFIX: &str = "mc";
+
+/// Format version of the bytes after the prefix.
+const VERSION: u8 = 0;
+
+/// Bytes of public key hash in an address.
+const HASH_LEN: usize = 20;
+
+const CHECKSUM_LEN: usize = 4;
+
+/// A checksummed address for one public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Address {
+    hash: [u8; HASH_LEN],
+}
+
+impl Address {
+    /// The address of an ed25519 public key.
+    pub fn from_public_key(public_key: &[u8; 32]) -> Self {
+        let digest = Sha256::digest(public_key);
+        let mut hash = [0; HASH_LEN];
+        hash.copy_from_slice(&digest[..HASH_LEN]);
+        Address { hash }
+    }
+
+    /// Whether this is the address of `public_key`.
+    pub fn matches(&self, public_key: &[u8; 32]) -> bool {
+        *self == Address::from_public_key(public_key)
+    }
+}
+
+fn checksum(payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let digest = Sha256::digest(Sha256::digest(payload));
+    let mut checksum = [0; CHECKSUM_LEN];
+    checksum.copy_from_slice(&digest[..CHECKSUM_LEN]);
+    checksum
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bytes = vec![VERSION];
+        bytes.extend_from_slice(&self.hash);
+        bytes.extend_from_slice(&checksum(&bytes));
+        write!(f, "{}{}", PREFIX, bs58::encode(bytes).into_string())
+    }
+}
+
+impl FromStr for Address {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = |why: &str| format!("invalid address {:?}: {}", s, why);
+        let encoded = s.strip_prefix(PREFIX).ok_or_else(|| invalid(&format!("expected it to start with {:?}", PREFIX)))?;
+        let bytes = bs58::decode(encoded).into_vec().map_err(|_| invalid("not Base58"))?;
+        if bytes.len() != 1 + HASH_LEN + CHECKSUM_LEN {
+            return Err(invalid("wrong length"));
+        }
+        let (payload, sum) = bytes.split_at(1 + HASH_LEN);
+        if checksum(payload) != sum {
+            return Err(invalid("checksum mismatch"));
+        }
+        if payload[0] != VERSION {
+            return Err(invalid(&format!("unknown version {}", payload[0])));
+        }
+        let mut hash = [0; HASH_LEN];
+        hash.copy_from_slice(&payload[1..]);
+        Ok(Address { hash })
+    }
+}
@@ -0,0 +1,36 @@
+//! Coin amounts.
+//!
+//! Amounts are stored as whole numbers of base units, [`COIN`] to the coin, and written
+//! for people as decimal coins.
+
+/// Base units in one coin.
+pub const COIN: u64 = 100_000_000;
+
+/// Decimal places in a coin amount.
+const DECIMALS: usize = 8;
+
+/// `amount` base units as decimal coins, without trailing zeros: `"12.5"`, `"50"`.
+pub fn format_coins(amount: u64) -> String {
+    let (whole, fraction) = (amount / COIN, amount % COIN);
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    let fraction = format!("{:0width$}", fraction, width = DECIMALS);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+/// Parse decimal coins such as `"12.5"` into base units.
+pub fn parse_coins(s: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid amount {:?}: expected coins with up to {} decimals", s, DECIMALS);
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if whole.is_empty() && fraction.is_empty() || fraction.len() > DECIMALS {
+        return Err(invalid());
+    }
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if !digits(whole) || !digits(fraction) {
+        return Err(invalid());
+    }
+    let whole: u64 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| invalid())? };
+    let fraction: u64 = format!("{:0<width$}", fraction, width = DECIMALS).parse().map_err(|_| invalid())?;
+    whole.checked_mul(COIN).and_then(|w| w.checked_add(fraction)).ok_or_else(invalid)
+}
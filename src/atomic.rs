@@ -0,0 +1,43 @@
+//! Crash-safe file replacement.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Extension of the temporary file written next to the target.
+pub const TEMP_EXTENSION: &str = "tmp";
+
+/// Replace `path` with `bytes` so that a crash at any point leaves either the old
+/// contents or the new ones, never a mix: write to `<path>.tmp`, fsync it, rename it over
+/// `path`, then fsync the directory so the rename itself is durable.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let temp = temp_path(path);
+    let mut file = File::create(&temp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temp, path)?;
+    sync_dir(path.parent().unwrap_or(Path::new("")))
+}
+
+/// Where [`write_atomic`] stages the new contents of `path`.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(TEMP_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// Flush directory entries (creations, renames) to disk. An empty path means the
+/// current directory.
+#[cfg(unix)]
+pub fn sync_dir(dir: &Path) -> io::Result<()> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    File::open(dir)?.sync_all()
+}
+
+/// Directories cannot be opened for syncing here; renames are durable once the call returns.
+#[cfg(not(unix))]
+pub fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
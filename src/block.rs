@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::header::{self, BITS_VERSION};
+use crate::hex;
+use crate::merkle;
+use crate::pow::Pow;
+use crate::transaction::Transaction;
+
+/// A mined block as stored on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Block {
+    /// Hash preimage format, see [`header`]. Missing in legacy files, which read as 0.
+    #[serde(default)]
+    pub version: u32,
+    pub index: u64,
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub body: Body,
+    pub nonce: u64,
+    /// Rolled by the miner each time `nonce` runs out. Hashed from version 3 on.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub extra_nonce: u64,
+    /// Leading zero bits the hash was mined against (leading zero hex digits before
+    /// version 2; see [`Block::difficulty_bits`]). `None` for legacy blocks written
+    /// before the difficulty was recorded; those are hashed without it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<usize>,
+    pub previous_hash: String,
+    /// Merkle root of `body`'s transactions, committed in the header from version 4 on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merkle_root: Option<String>,
+    pub hash: String,
+    pub mining_duration_ms: u128,
+    /// Host the block was mined on. Informational only; not part of the hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+}
+
+/// What a block carries. Stored inline in the block's JSON as either a `data` string or
+/// a `transactions` list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Body {
+    /// A single free-form string, as blocks before version 4 carry.
+    Legacy { data: String },
+    /// Any number of records, committed to by the header's Merkle root.
+    Transactions { transactions: Vec<Transaction> },
+}
+
+impl Body {
+    /// The transactions, or `None` for a legacy body.
+    pub fn transactions(&self) -> Option<&[Transaction]> {
+        match self {
+            Body::Legacy { .. } => None,
+            Body::Transactions { transactions } => Some(transactions),
+        }
+    }
+
+    /// Merkle root of the transactions, hex encoded; `None` for a legacy body.
+    pub fn merkle_root(&self) -> Option<String> {
+        self.transactions().map(|txs| hex::encode(&merkle::transactions_root(txs)))
+    }
+}
+
+impl Block {
+    /// Recompute this block's hash from its fields under the chain's `pow` algorithm.
+    pub fn compute_hash(&self, pow: Pow) -> Result<String> {
+        Ok(hex::encode(&pow.hash(&header::preimage(self)?)))
+    }
+
+    /// Recorded difficulty in leading zero bits, whatever unit the block's version uses.
+    pub fn difficulty_bits(&self) -> Option<usize> {
+        self.difficulty.map(|d| if self.version < BITS_VERSION { d * 4 } else { d })
+    }
+
+    /// Leading zero bits this block must have: the recorded difficulty, or `legacy` if
+    /// none was recorded.
+    pub fn effective_difficulty(&self, legacy: usize) -> usize {
+        self.difficulty_bits().unwrap_or(legacy)
+    }
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+/// SHA-256 over the block's header preimage, hex encoded: the hash of blocks on chains
+/// using the default [`Pow`].
+pub fn calculate_hash(block: &Block) -> Result<String> {
+    block.compute_hash(Pow::Sha256)
+}
+
+/// Number of leading zero bits in `digest`.
+pub fn leading_zero_bits(digest: &[u8]) -> usize {
+    let zero_bytes = digest.iter().take_while(|&&b| b == 0).count();
+    let partial = digest.get(zero_bytes).map(|b| b.leading_zeros() as usize).unwrap_or(0);
+    zero_bytes * 8 + partial
+}
+
+/// Whether `digest` starts with at least `bits` zero bits.
+pub fn meets_difficulty(digest: &[u8], bits: usize) -> bool {
+    let (whole, rest) = (bits / 8, bits % 8);
+    if digest.len() < whole + (rest > 0) as usize || digest[..whole].iter().any(|&b| b != 0) {
+        return false;
+    }
+    rest == 0 || digest[whole] >> (8 - rest) == 0
+}
@@ -0,0 +1,70 @@
+use crate::block::Block;
+use crate::consensus::Consensus;
+use crate::error::Result;
+use crate::miner::{Mined, Miner, Progress};
+use crate::transaction::Transaction;
+use crate::verify::{verify_blocks, Violation};
+
+/// Data committed in the first block of every chain.
+pub const GENESIS_DATA: &str = "Genesis Block";
+
+/// `previous_hash` of the genesis block.
+pub const GENESIS_PREVIOUS_HASH: &str = "0";
+
+/// An ordered list of blocks.
+#[derive(Debug, Clone, Default)]
+pub struct Chain {
+    blocks: Vec<Block>,
+}
+
+impl Chain {
+    pub fn new(blocks: Vec<Block>) -> Self {
+        Chain { blocks }
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn tip(&self) -> Option<&Block> {
+        self.blocks.last()
+    }
+
+    /// Index the next appended block will get.
+    pub fn next_index(&self) -> u64 {
+        self.tip().map(|b| b.index + 1).unwrap_or(0)
+    }
+
+    /// Mine the next block on top of the tip, or the genesis block if the chain is empty.
+    pub fn mine_next(&mut self, miner: &Miner, transactions: Vec<Transaction>) -> Result<Mined> {
+        self.resume_next(miner, transactions, Progress::default())
+    }
+
+    /// Like [`Chain::mine_next`], continuing a cancelled search for the same block.
+    pub fn resume_next(&mut self, miner: &Miner, transactions: Vec<Transaction>, from: Progress) -> Result<Mined> {
+        let mined = match self.tip() {
+            Some(tip) => miner.resume(tip.index + 1, transactions, &tip.hash, from)?,
+            None => miner.resume(0, transactions, GENESIS_PREVIOUS_HASH, from)?,
+        };
+        self.blocks.push(mined.block.clone());
+        Ok(mined)
+    }
+
+    /// Difficulty the next block must be mined at, if the chain retargets.
+    pub fn next_difficulty(&self, consensus: &Consensus) -> Option<usize> {
+        consensus.expected_difficulty(&self.blocks)
+    }
+
+    /// Fully verify the chain under `consensus`, returning every violation found.
+    pub fn verify(&self, consensus: &Consensus) -> Vec<Violation> {
+        verify_blocks(&self.blocks, consensus)
+    }
+}
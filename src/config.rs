@@ -0,0 +1,42 @@
+//! Optional JSON configuration file for the CLI.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::Result;
+use crate::platform::PlatformPolicy;
+
+/// File read when no `--config` path is given.
+pub const DEFAULT_CONFIG_FILE: &str = "mchain.json";
+
+/// Settings read from the config file. Every field is optional; command-line flags and
+/// environment variables take precedence over anything set here.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// One of `require-apple-silicon`, `warn` or `any`.
+    pub platform_policy: Option<String>,
+    /// Directory holding the block store and named chains.
+    pub data_dir: Option<PathBuf>,
+}
+
+impl Config {
+    /// Load `path`, or return the default config if it does not exist.
+    pub fn load(path: &Path) -> Result<Config> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn platform_policy(&self) -> Result<Option<PlatformPolicy>> {
+        self.platform_policy
+            .as_deref()
+            .map(|s| s.parse().map_err(crate::Error::Config))
+            .transpose()
+    }
+}
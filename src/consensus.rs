@@ -0,0 +1,165 @@
+//! Chain-wide rules beyond what a single block can prove about itself.
+
+use serde::{Deserialize, Serialize};
+
+use crate::amount::COIN;
+use crate::block::Block;
+use crate::pow::Pow;
+use crate::transaction::{Output, Transaction};
+
+/// Difficulty retargeting, fixed when a chain is created and recorded in its
+/// `chain.json`.
+///
+/// Every `interval` blocks the difficulty is recomputed from how long the previous
+/// `interval` blocks took according to their timestamps, rounded to the nearest step of
+/// `unit`. With bit steps (2x the work each) the difficulty goes up a step when those
+/// blocks came at least √2x faster than `target_block_secs`; with hex-digit steps (16x)
+/// it takes 4x. It never moves more than `max_step` steps at once or below one step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Retarget {
+    /// Difficulty of the first `interval` blocks, in `unit`s.
+    pub initial_difficulty: usize,
+    /// Blocks between adjustments. At least 2.
+    pub interval: u64,
+    /// Desired seconds between blocks.
+    pub target_block_secs: u64,
+    /// Largest change in difficulty per adjustment, in `unit`s.
+    #[serde(default = "default_max_step")]
+    pub max_step: usize,
+    /// Granularity of `initial_difficulty`, `max_step` and each adjustment.
+    #[serde(default)]
+    pub unit: DifficultyUnit,
+}
+
+fn default_max_step() -> usize {
+    1
+}
+
+/// Size of one retargeting step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DifficultyUnit {
+    /// One leading zero hex digit (4 bits), as chains created before bit targets use.
+    #[default]
+    HexDigits,
+    /// One leading zero bit.
+    Bits,
+}
+
+impl DifficultyUnit {
+    /// Leading zero bits per step.
+    pub fn bits(self) -> usize {
+        match self {
+            DifficultyUnit::HexDigits => 4,
+            DifficultyUnit::Bits => 1,
+        }
+    }
+}
+
+/// Block reward schedule, fixed when a chain is created and recorded in its `chain.json`.
+///
+/// Each block's coinbase may pay out the block's subsidy plus the fees of its other
+/// transactions. The subsidy starts at `initial_subsidy` and halves every
+/// `halving_interval` blocks until it rounds down to nothing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reward {
+    /// Subsidy of the first `halving_interval` blocks, in base units.
+    pub initial_subsidy: u64,
+    /// Blocks between halvings. At least 1.
+    pub halving_interval: u64,
+}
+
+impl Default for Reward {
+    /// 50 coins, halving every 210 blocks.
+    fn default() -> Self {
+        Reward { initial_subsidy: 50 * COIN, halving_interval: 210 }
+    }
+}
+
+/// Everything `verify` enforces beyond the per-block checks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Consensus {
+    /// Lowest recorded difficulty accepted, and the difficulty legacy blocks that don't
+    /// record one must meet.
+    /// Both in leading zero bits.
+    pub min_difficulty: usize,
+    /// Retargeting rule, for chains created with one.
+    pub retarget: Option<Retarget>,
+    /// Hash function every block is mined against.
+    pub pow: Pow,
+    /// Block reward schedule, for chains created with one. Every block of such a chain
+    /// starts with a coinbase.
+    pub reward: Option<Reward>,
+}
+
+impl Retarget {
+    /// A rule in bit steps starting at `initial_bits`, moving at most 2 bits (4x the
+    /// work) per adjustment.
+    pub fn new(initial_bits: usize, interval: u64, target_block_secs: u64) -> Self {
+        Retarget {
+            initial_difficulty: initial_bits,
+            interval: interval.max(2),
+            target_block_secs,
+            max_step: 2,
+            unit: DifficultyUnit::Bits,
+        }
+    }
+
+    /// Difficulty in leading zero bits required of the block following `prior`.
+    pub fn next_difficulty(&self, prior: &[Block]) -> usize {
+        let step = self.unit.bits();
+        let initial = self.initial_difficulty * step;
+        let height = prior.len() as u64;
+        let Some(last) = prior.last() else {
+            return initial;
+        };
+        let current = last.difficulty_bits().unwrap_or(initial);
+        let interval = self.interval.max(2);
+        if !height.is_multiple_of(interval) {
+            return current;
+        }
+
+        let first = &prior[(height - interval) as usize];
+        let actual = last.timestamp.saturating_sub(first.timestamp).max(1) as u128;
+        let expected = self.target_block_secs as u128 * (interval - 1) as u128;
+        // Round to the nearest step: `n` steps up once blocks came base^(n - 1/2) times too
+        // fast, and likewise down when too slow. Squared to stay in integers.
+        let base = 1u128 << step;
+        let threshold = |steps: usize| base.saturating_pow(2 * steps as u32 + 1);
+        let (actual, expected) = (actual.saturating_mul(actual), expected.saturating_mul(expected));
+        let mut up = 0;
+        while up < self.max_step && actual.saturating_mul(threshold(up)) <= expected {
+            up += 1;
+        }
+        let mut down = 0;
+        while down < self.max_step && expected.saturating_mul(threshold(down)) <= actual {
+            down += 1;
+        }
+        (current + up * step).saturating_sub(down * step).max(step)
+    }
+}
+
+impl Reward {
+    /// Newly created coins the block at `height` may claim.
+    pub fn subsidy(&self, height: u64) -> u64 {
+        let halvings = height / self.halving_interval.max(1);
+        self.initial_subsidy.checked_shr(halvings.try_into().unwrap_or(u32::MAX)).unwrap_or(0)
+    }
+
+    /// Coinbase for the block at `height` paying its subsidy plus `fees` to `address`, or
+    /// paying nothing out if there is no address to pay.
+    pub fn coinbase(&self, height: u64, fees: u64, address: Option<&str>) -> Transaction {
+        let outputs = address
+            .map(|address| Output { address: address.to_string(), amount: self.subsidy(height).saturating_add(fees) })
+            .into_iter()
+            .collect();
+        Transaction::coinbase(height, outputs)
+    }
+}
+
+impl Consensus {
+    /// Difficulty the block following `prior` must record, if the chain retargets.
+    pub fn expected_difficulty(&self, prior: &[Block]) -> Option<usize> {
+        self.retarget.as_ref().map(|r| r.next_difficulty(prior))
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+use std::io;
+
+use crate::miner::Progress;
+use crate::store::{Backend, LoadError};
+
+/// Errors returned by the MChain library.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing the block store failed.
+    Io(io::Error),
+    /// A block could not be serialized or deserialized.
+    Json(serde_json::Error),
+    /// A block uses a header version this build cannot hash.
+    UnsupportedVersion(u32),
+    /// A hash field is not 64 hex digits.
+    MalformedHash(String),
+    /// A block's body (data string or transactions) doesn't match its header version.
+    BodyMismatch(u32),
+    /// A difficulty does not fit in the binary header.
+    DifficultyOutOfRange(usize),
+    /// The stored block files are corrupt or do not form a contiguous chain.
+    Load(LoadError),
+    /// A block was appended whose index does not follow the store's tip.
+    OutOfSequence { expected: u64, found: u64 },
+    /// A chain was opened with a different backend than it was created with.
+    BackendMismatch { recorded: Backend, requested: Backend },
+    /// Moving a chain to another backend failed; the original is left in place.
+    Migration(String),
+    /// The chain needs a cargo feature this build was compiled without.
+    FeatureDisabled(&'static str),
+    /// The SQLite block store failed.
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+    /// A chain name contains characters other than letters, digits, `_` and `-`.
+    InvalidChainName(String),
+    /// The configuration file has an invalid value.
+    Config(String),
+    /// An inclusion proof could not be built.
+    Proof(String),
+    /// Mining was cancelled before a block was found.
+    Cancelled { progress: Progress, hashes: u64 },
+    /// A wallet could not be created, read or unlocked.
+    Wallet(String),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Json(e) => write!(f, "JSON error: {}", e),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported block version {}", v),
+            Error::MalformedHash(h) => write!(f, "malformed hash {:?}", h),
+            Error::BodyMismatch(v) => write!(f, "block body does not match block version {}", v),
+            Error::DifficultyOutOfRange(d) => write!(f, "difficulty {} out of range", d),
+            Error::Load(e) => write!(f, "{}", e),
+            Error::OutOfSequence { expected, found } => {
+                write!(f, "cannot append block {}: the store expects block {}", found, expected)
+            }
+            Error::BackendMismatch { recorded, requested } => {
+                write!(f, "chain uses the {} store backend, not {}", recorded, requested)
+            }
+            Error::Migration(msg) => write!(f, "migration failed: {}", msg),
+            Error::FeatureDisabled(feature) => {
+                write!(f, "this build does not support it; rebuild with `--features {}`", feature)
+            }
+            #[cfg(feature = "sqlite")]
+            Error::Sqlite(e) => write!(f, "SQLite error: {}", e),
+            Error::InvalidChainName(name) => {
+                write!(f, "invalid chain name {:?}: use letters, digits, '_' and '-'", name)
+            }
+            Error::Config(msg) => write!(f, "config error: {}", msg),
+            Error::Proof(msg) => write!(f, "cannot build proof: {}", msg),
+            Error::Cancelled { hashes, .. } => write!(f, "mining cancelled after {} hashes", hashes),
+            Error::Wallet(msg) => write!(f, "wallet error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Load(e) => Some(e),
+            #[cfg(feature = "sqlite")]
+            Error::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Sqlite(e)
+    }
+}
@@ -0,0 +1,141 @@
+//! Hierarchical deterministic keys.
+//!
+//! A wallet's keys all grow from one BIP39 mnemonic: the phrase is stretched into a
+//! 64-byte seed, the seed into a master key, and the master key into a tree of child
+//! keys by SLIP-0010, the ed25519 flavour of BIP32. Ed25519 only allows hardened
+//! derivation, so every step of the path is hardened and deriving a new address needs
+//! the secret.
+
+use std::fmt;
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+
+use crate::error::Result;
+use crate::wallet::{random, Keypair};
+
+/// BIP44 purpose.
+const PURPOSE: u32 = 44;
+
+/// SLIP-0044 coin type shared by test networks.
+const COIN_TYPE: u32 = 1;
+
+/// Added to an index to make it hardened.
+const HARDENED: u32 = 1 << 31;
+
+/// Bytes of entropy behind a new mnemonic: 24 words.
+const ENTROPY_BYTES: usize = 32;
+
+/// A BIP39 seed phrase in English.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mnemonic(bip39::Mnemonic);
+
+impl Mnemonic {
+    /// A new 24-word phrase from the operating system's random number generator.
+    pub fn generate() -> Result<Self> {
+        let entropy: [u8; ENTROPY_BYTES] = random()?;
+        Ok(Mnemonic::from_entropy(&entropy).expect("32 bytes is a valid entropy length"))
+    }
+
+    /// The phrase encoding `entropy`, which must be 16 to 32 bytes in steps of 4.
+    pub fn from_entropy(entropy: &[u8]) -> Option<Self> {
+        bip39::Mnemonic::from_entropy(entropy).ok().map(Mnemonic)
+    }
+
+    pub fn entropy(&self) -> Vec<u8> {
+        self.0.to_entropy()
+    }
+
+    pub fn word_count(&self) -> usize {
+        self.0.word_count()
+    }
+
+    /// The BIP39 seed, with an empty passphrase.
+    pub fn seed(&self) -> [u8; 64] {
+        self.0.to_seed("")
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Mnemonic {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let words = s.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        bip39::Mnemonic::parse_normalized(&words).map(Mnemonic).map_err(|e| format!("invalid seed phrase: {}", e))
+    }
+}
+
+/// A node of the key tree: a secret key and the chain code its children are derived with.
+#[derive(Clone)]
+pub struct ExtendedKey {
+    secret: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+impl ExtendedKey {
+    /// The root of the tree grown from `seed`.
+    pub fn master(seed: &[u8]) -> Self {
+        ExtendedKey::from_hmac(b"ed25519 seed", seed)
+    }
+
+    /// The hardened child `index`, counting from 0.
+    pub fn child(&self, index: u32) -> Self {
+        let mut data = vec![0];
+        data.extend_from_slice(&self.secret);
+        data.extend_from_slice(&(index | HARDENED).to_be_bytes());
+        ExtendedKey::from_hmac(&self.chain_code, &data)
+    }
+
+    /// Follow `path` of hardened indexes down from this key.
+    pub fn derive(&self, path: &[u32]) -> Self {
+        path.iter().fold(self.clone(), |key, &index| key.child(index))
+    }
+
+    pub fn secret(&self) -> [u8; 32] {
+        self.secret
+    }
+
+    pub fn chain_code(&self) -> [u8; 32] {
+        self.chain_code
+    }
+
+    pub fn keypair(&self) -> Keypair {
+        Keypair::from_secret(self.secret)
+    }
+
+    fn from_hmac(key: &[u8], data: &[u8]) -> Self {
+        let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC takes keys of any length");
+        mac.update(data);
+        let out = mac.finalize().into_bytes();
+        let (secret, chain_code) = out.split_at(32);
+        ExtendedKey { secret: secret.try_into().unwrap(), chain_code: chain_code.try_into().unwrap() }
+    }
+}
+
+impl fmt::Debug for ExtendedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtendedKey").finish_non_exhaustive()
+    }
+}
+
+/// Hardened indexes of address `index` of `account`: `m/44'/1'/account'/0'/index'`.
+pub fn path(account: u32, index: u32) -> [u32; 5] {
+    [PURPOSE, COIN_TYPE, account, 0, index]
+}
+
+/// [`path`] written out, as in `m/44'/1'/0'/0'/3'`.
+pub fn path_string(account: u32, index: u32) -> String {
+    path(account, index).iter().fold("m".to_string(), |path, index| format!("{}/{}'", path, index))
+}
+
+/// The key at address `index` of `account` under `seed`.
+pub fn derive(seed: &[u8], account: u32, index: u32) -> Keypair {
+    ExtendedKey::master(seed).derive(&path(account, index)).keypair()
+}
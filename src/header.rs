@@ -0,0 +1,192 @@
+//! Hash preimages for block headers.
+//!
+//! Version 0 is the legacy format: the decimal fields concatenated as a string, which is
+//! ambiguous (index 1 + timestamp 23 encodes like index 12 + timestamp 3) but kept so old
+//! blocks still verify. Version 1 is a fixed binary layout, all integers little-endian:
+//!
+//! ```text
+//! version u32 | index u64 | timestamp u64 | difficulty u32 | nonce u64
+//! | previous_hash [u8; 32] | data_len u64 | data [u8; data_len]
+//! ```
+//!
+//! The genesis `previous_hash` of `"0"` encodes as 32 zero bytes.
+//!
+//! Version 2 has the same layout as version 1, but its `difficulty` counts leading zero
+//! bits of the hash rather than leading zero hex digits.
+//!
+//! Version 3 adds an `extra_nonce u64` right after the nonce, which the miner rolls once
+//! the nonce space is used up.
+//!
+//! Version 4 blocks carry transactions instead of a data string, and the header commits
+//! to them through their Merkle root in place of the data, giving a fixed 104 bytes:
+//!
+//! ```text
+//! version u32 | index u64 | timestamp u64 | difficulty u32 | nonce u64 | extra_nonce u64
+//! | previous_hash [u8; 32] | merkle_root [u8; 32]
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::{Block, Body};
+use crate::chain::GENESIS_PREVIOUS_HASH;
+use crate::error::{Error, Result};
+use crate::hex;
+use crate::pow::Pow;
+
+/// Concatenated-string preimage used by blocks without a `version`.
+pub const LEGACY_VERSION: u32 = 0;
+
+/// Length-prefixed binary preimage.
+pub const BINARY_VERSION: u32 = 1;
+
+/// Binary preimage with the difficulty in bits.
+pub const BITS_VERSION: u32 = 2;
+
+/// Binary preimage with an extra nonce after the nonce.
+pub const EXTRA_NONCE_VERSION: u32 = 3;
+
+/// Fixed-size header committing to a Merkle root of transactions.
+pub const MERKLE_VERSION: u32 = 4;
+
+/// Version written by the miner.
+pub const CURRENT_VERSION: u32 = MERKLE_VERSION;
+
+/// Byte offset of the timestamp in a binary preimage.
+pub const TIMESTAMP_OFFSET: usize = 4 + 8;
+
+/// Byte offset of the nonce in a binary preimage, so miners can patch it in place.
+pub const NONCE_OFFSET: usize = TIMESTAMP_OFFSET + 8 + 4;
+
+/// Byte offset of the extra nonce in a version 3 preimage.
+pub const EXTRA_NONCE_OFFSET: usize = NONCE_OFFSET + 8;
+
+/// Build the bytes hashed for `block` under its own version's rules.
+pub fn preimage(block: &Block) -> Result<Vec<u8>> {
+    match block.version {
+        LEGACY_VERSION => Ok(legacy_preimage(block)?.into_bytes()),
+        BINARY_VERSION | BITS_VERSION | EXTRA_NONCE_VERSION | MERKLE_VERSION => binary_preimage(block),
+        v => Err(Error::UnsupportedVersion(v)),
+    }
+}
+
+fn legacy_preimage(block: &Block) -> Result<String> {
+    let mut input = format!(
+        "{}{}{}{}{}",
+        block.index, block.timestamp, legacy_data(block)?, block.nonce, block.previous_hash
+    );
+    if let Some(difficulty) = block.difficulty {
+        input.push_str(&difficulty.to_string());
+    }
+    Ok(input)
+}
+
+/// The data string of a block from before version 4.
+fn legacy_data(block: &Block) -> Result<&str> {
+    match &block.body {
+        Body::Legacy { data } => Ok(data),
+        Body::Transactions { .. } => Err(Error::BodyMismatch(block.version)),
+    }
+}
+
+/// Binary preimage of a version 1–3 block, which hashes its data string directly.
+fn binary_preimage(block: &Block) -> Result<Vec<u8>> {
+    if block.version >= MERKLE_VERSION {
+        return BlockHeader::of(block)?.preimage();
+    }
+    let data = legacy_data(block)?;
+    let mut out = Vec::with_capacity(EXTRA_NONCE_OFFSET + 8 + 32 + 8 + data.len());
+    put_fixed_fields(&mut out, block.version, block.index, block.timestamp, block.difficulty, block.nonce)?;
+    if block.version >= EXTRA_NONCE_VERSION {
+        out.extend_from_slice(&block.extra_nonce.to_le_bytes());
+    }
+    out.extend_from_slice(&hash_bytes(&block.previous_hash)?);
+    out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    out.extend_from_slice(data.as_bytes());
+    Ok(out)
+}
+
+/// The fields every binary preimage starts with, up to and including the nonce.
+fn put_fixed_fields(
+    out: &mut Vec<u8>,
+    version: u32,
+    index: u64,
+    timestamp: u64,
+    difficulty: Option<usize>,
+    nonce: u64,
+) -> Result<()> {
+    let difficulty = difficulty.unwrap_or(0);
+    let difficulty = u32::try_from(difficulty).map_err(|_| Error::DifficultyOutOfRange(difficulty))?;
+    out.extend_from_slice(&version.to_le_bytes());
+    out.extend_from_slice(&index.to_le_bytes());
+    out.extend_from_slice(&timestamp.to_le_bytes());
+    out.extend_from_slice(&difficulty.to_le_bytes());
+    out.extend_from_slice(&nonce.to_le_bytes());
+    Ok(())
+}
+
+/// The hashed fields of a version 4 or later block: everything but its transactions,
+/// which the Merkle root stands in for. Enough to check a block's proof of work and its
+/// link to the previous block without the body.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub version: u32,
+    pub index: u64,
+    pub timestamp: u64,
+    /// Leading zero bits.
+    pub difficulty: usize,
+    pub nonce: u64,
+    pub extra_nonce: u64,
+    pub previous_hash: String,
+    pub merkle_root: String,
+}
+
+impl BlockHeader {
+    /// Header of `block`. Fails for blocks from before version 4, which hash their data
+    /// string whole, and for blocks missing their Merkle root.
+    pub fn of(block: &Block) -> Result<BlockHeader> {
+        let (Some(_), Some(merkle_root)) = (block.body.transactions(), &block.merkle_root) else {
+            return Err(Error::BodyMismatch(block.version));
+        };
+        if block.version < MERKLE_VERSION {
+            return Err(Error::BodyMismatch(block.version));
+        }
+        Ok(BlockHeader {
+            version: block.version,
+            index: block.index,
+            timestamp: block.timestamp,
+            difficulty: block.difficulty.unwrap_or(0),
+            nonce: block.nonce,
+            extra_nonce: block.extra_nonce,
+            previous_hash: block.previous_hash.clone(),
+            merkle_root: merkle_root.clone(),
+        })
+    }
+
+    /// The fixed 104-byte preimage.
+    pub fn preimage(&self) -> Result<Vec<u8>> {
+        if self.version < MERKLE_VERSION || self.version > CURRENT_VERSION {
+            return Err(Error::UnsupportedVersion(self.version));
+        }
+        let mut out = Vec::with_capacity(EXTRA_NONCE_OFFSET + 8 + 32 + 32);
+        put_fixed_fields(&mut out, self.version, self.index, self.timestamp, Some(self.difficulty), self.nonce)?;
+        out.extend_from_slice(&self.extra_nonce.to_le_bytes());
+        out.extend_from_slice(&hash_bytes(&self.previous_hash)?);
+        out.extend_from_slice(&hash_bytes(&self.merkle_root)?);
+        Ok(out)
+    }
+
+    /// The block hash under `pow`, hex encoded.
+    pub fn hash(&self, pow: Pow) -> Result<String> {
+        Ok(hex::encode(&pow.hash(&self.preimage()?)))
+    }
+}
+
+/// Decode a 64-digit hex hash, mapping the genesis marker to all zeros.
+pub fn hash_bytes(hash: &str) -> Result<[u8; 32]> {
+    if hash == GENESIS_PREVIOUS_HASH {
+        return Ok([0; 32]);
+    }
+    hex::decode(hash)
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| Error::MalformedHash(hash.to_string()))
+}
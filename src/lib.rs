@@ -0,0 +1,42 @@
+//! MChain: a small proof-of-work blockchain with filesystem persistence.
+//!
+//! The `mchain` binary is a thin CLI over this library.
+
+pub mod address;
+pub mod amount;
+pub mod atomic;
+pub mod block;
+pub mod chain;
+pub mod config;
+pub mod consensus;
+pub mod error;
+pub mod hd;
+pub mod header;
+mod hex;
+pub mod mempool;
+pub mod merkle;
+pub mod miner;
+pub mod platform;
+pub mod pow;
+pub mod proof;
+pub mod session;
+pub mod store;
+pub mod transaction;
+pub mod utxo;
+pub mod verify;
+pub mod wallet;
+
+pub use address::Address;
+pub use block::{calculate_hash, Block, Body};
+pub use chain::Chain;
+pub use consensus::{Consensus, DifficultyUnit, Retarget, Reward};
+pub use error::{Error, Result};
+pub use mempool::Mempool;
+pub use miner::{Mined, Miner, Progress};
+pub use platform::{Platform, PlatformPolicy};
+pub use pow::{Pow, PowAlgorithm};
+pub use store::{Backend, BlockStore, DirStore, LoadError, LogStore, MemoryStore};
+pub use transaction::{Input, OutPoint, Output, Transaction};
+pub use utxo::UtxoSet;
+pub use verify::{verify_blocks, Violation};
+pub use wallet::{Keypair, Keystore};
@@ -1,27 +1,51 @@
-use sha2::{Sha256, Digest};
-use std::time::{SystemTime, UNIX_EPOCH, Instant};
-use std::process::{Command, exit};
-use std::fs::{self, File};
-use std::io::{Write, BufReader};
-use std::path::Path;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use clap::{Parser, Subcommand};
-use serde::{Serialize, Deserialize};
+use mchain::address::Address;
+use mchain::amount::{format_coins, parse_coins};
+use mchain::chain::GENESIS_DATA;
+use mchain::config::{Config, DEFAULT_CONFIG_FILE};
+use mchain::hd::{self, Mnemonic};
+use mchain::mempool::{Mempool, DEFAULT_MAX_BLOCK_BYTES};
+use mchain::platform::Verdict;
+use mchain::proof::InclusionProof;
+use mchain::session::Session;
+use mchain::store::{self, ChainConfig, DEFAULT_DIR};
+use mchain::wallet::Keystore;
+use mchain::{Backend, Block, BlockStore, Chain, Consensus, Error, Mined, Miner, Platform, PlatformPolicy, Pow, Retarget, Reward, Transaction, UtxoSet};
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Block {
-    index: u64,
-    timestamp: u64,
-    data: String,
-    nonce: u64,
-    previous_hash: String,
-    hash: String,
-    mining_duration_ms: u128,
-}
+/// Difficulty in leading zero bits used when none is given.
+const DEFAULT_BITS: usize = 20;
 
 #[derive(Parser, Debug)]
 #[command(name = "mchain")]
 #[command(about = "Mine or manage MChain blocks on Apple Silicon")]
 struct Args {
+    /// JSON config file
+    #[arg(long, global = true, env = "MCHAIN_CONFIG", default_value = DEFAULT_CONFIG_FILE)]
+    config: PathBuf,
+    /// Which hosts may run MChain: require-apple-silicon, warn or any
+    #[arg(long, global = true, env = "MCHAIN_PLATFORM_POLICY")]
+    platform_policy: Option<PlatformPolicy>,
+    /// Directory holding all chains [default: mchain_data]
+    #[arg(long, global = true, env = "MCHAIN_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+    /// Named chain to operate on, stored in <data-dir>/chains/<name>
+    #[arg(long, global = true, env = "MCHAIN_CHAIN")]
+    chain: Option<String>,
+    /// Storage backend for a new chain: json, log or memory [default: json]
+    #[arg(long, global = true, env = "MCHAIN_STORE")]
+    store: Option<Backend>,
+    /// Refuse to proceed if any block file is corrupt or out of place
+    #[arg(long, global = true)]
+    strict: bool,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -32,172 +56,759 @@ enum Commands {
     Mine {
         #[arg(short, long, default_value_t = 3)]
         blocks: u64,
-        #[arg(short = 'l', long, default_value_t = 5)]
-        difficulty: usize,
+        /// Difficulty in leading zero hex digits (4 bits each)
+        #[arg(short = 'l', long, conflicts_with = "bits")]
+        difficulty: Option<usize>,
+        /// Difficulty in leading zero bits, or the initial difficulty of a new
+        /// retargeting chain [default: 20]
+        #[arg(long)]
+        bits: Option<usize>,
         #[arg(short, long, default_value = "MChain data")]
         data: String,
+        /// Worker threads (default: all cores)
+        #[arg(short, long)]
+        threads: Option<usize>,
+        /// Create the chain with difficulty retargeting every N blocks
+        #[arg(long, value_name = "N")]
+        retarget_interval: Option<u64>,
+        /// Desired seconds between blocks on a new retargeting chain
+        #[arg(long, default_value_t = 60, requires = "retarget_interval")]
+        target_block_secs: u64,
+        /// Proof-of-work hash for a new chain: sha256, sha256d, blake3 or argon2id
+        /// [default: sha256]
+        #[arg(long)]
+        pow: Option<Pow>,
+        /// Address to pay the block rewards to
+        #[arg(long, env = "MCHAIN_MINER_ADDRESS")]
+        miner_address: Option<Address>,
+        /// Block subsidy of a new chain, in coins, before its first halving [default: 50]
+        #[arg(long, value_parser = parse_coins)]
+        block_reward: Option<u64>,
+        /// Blocks between halvings of the subsidy on a new chain [default: 210]
+        #[arg(long, value_name = "N")]
+        halving_interval: Option<u64>,
+        /// Signed transaction file, as written by `tx send`, to add to the mempool before
+        /// mining; repeatable
+        #[arg(long = "tx", value_name = "FILE")]
+        txs: Vec<PathBuf>,
+        /// Most bytes of encoded transactions to put in a block
+        #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_BLOCK_BYTES)]
+        max_block_size: usize,
+        /// Continue the run that was interrupted, with its block count, data and miner address
+        #[arg(long, conflicts_with_all = ["blocks", "difficulty", "bits", "data", "retarget_interval", "pow", "miner_address", "block_reward", "halving_interval", "txs"])]
+        resume: bool,
     },
     /// Verify integrity of stored blocks
-    Verify,
+    Verify {
+        /// Minimum accepted difficulty in leading zero hex digits, also applied to legacy
+        /// blocks that don't record one
+        #[arg(short = 'l', long, conflicts_with = "bits")]
+        difficulty: Option<usize>,
+        /// Minimum accepted difficulty in leading zero bits [default: the chain's own
+        /// floor, 1 on retargeting chains, or 20 for legacy blocks]
+        #[arg(long)]
+        bits: Option<usize>,
+    },
     /// List existing blocks
     List,
+    /// Move corrupt or out-of-place block files into the corrupt/ subdirectory
+    Repair,
     /// Delete all stored blocks
     Reset,
+    /// List named chains in the data directory
+    Chains,
+    /// Move the chain to another storage backend
+    Migrate {
+        /// Target backend: json, log or sqlite
+        #[arg(long)]
+        to: Backend,
+    },
+    /// Write a JSON proof that a transaction is in a block
+    Prove {
+        /// Index of the block holding the transaction
+        #[arg(long)]
+        block: u64,
+        /// Position of the transaction in the block
+        #[arg(long)]
+        item: usize,
+        /// Block to carry the header chain up to [default: the tip]
+        #[arg(long)]
+        checkpoint: Option<u64>,
+        /// Write the proof to this file instead of stdout
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Check a proof written by `prove`, without access to the chain
+    VerifyProof {
+        proof: PathBuf,
+        /// Hash the checkpoint block is known to have
+        #[arg(long)]
+        checkpoint_hash: Option<String>,
+    },
+    /// Manage the keypairs in <data-dir>/wallets
+    Wallet {
+        #[command(subcommand)]
+        command: WalletCommand,
+    },
+    /// Build and sign transactions
+    Tx {
+        #[command(subcommand)]
+        command: TxCommand,
+    },
+    /// Show the unspent coins paid to an address or a wallet
+    Balance {
+        #[arg(required_unless_present = "wallet")]
+        address: Option<Address>,
+        /// Add up every address of this wallet instead
+        #[arg(long, conflicts_with = "address")]
+        wallet: Option<String>,
+    },
+    /// Rebuild the unspent output set from every stored block
+    Reindex,
+    /// Orphan every block above HEIGHT, returning their transactions to the mempool
+    Rewind { height: u64 },
 }
 
-fn is_apple_silicon() -> bool {
-    let output = Command::new("sysctl")
-        .arg("-n")
-        .arg("machdep.cpu.brand_string")
-        .output()
-        .expect("Failed to execute sysctl");
-    let cpu_info = String::from_utf8_lossy(&output.stdout);
-    cpu_info.contains("Apple M")
-}
-
-fn calculate_hash(index: u64, timestamp: u64, data: &str, nonce: u64, previous_hash: &str) -> String {
-    let input = format!("{}{}{}{}{}", index, timestamp, data, nonce, previous_hash);
-    let mut hasher = Sha256::new();
-    hasher.update(input.as_bytes());
-    format!("{:x}", hasher.finalize())
-}
-
-fn mine_block(index: u64, data: &str, previous_hash: &str, difficulty: usize) -> Block {
-    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-    let mut nonce = 0;
-    let prefix = "0".repeat(difficulty);
-    let start = Instant::now();
-
-    loop {
-        let hash = calculate_hash(index, timestamp, data, nonce, previous_hash);
-        if hash.starts_with(&prefix) {
-            let elapsed = start.elapsed().as_millis();
-            println!("✅ Block {} mined in {} ms! Nonce: {}, Hash: {}", index, elapsed, nonce, hash);
-            return Block {
-                index,
-                timestamp,
-                data: data.to_string(),
-                nonce,
-                previous_hash: previous_hash.to_string(),
-                hash,
-                mining_duration_ms: elapsed,
-            };
+#[derive(Subcommand, Debug)]
+enum WalletCommand {
+    /// Create a wallet with a new seed phrase
+    New { name: String },
+    /// Rebuild a wallet from its seed phrase and find its addresses on the chain
+    Restore {
+        name: String,
+        /// Account to derive addresses for
+        #[arg(long, default_value_t = 0)]
+        account: u32,
+    },
+    /// Derive a wallet's next address
+    Address { name: String },
+    /// List wallets and their addresses
+    List,
+    /// Show a wallet's addresses and public keys
+    Show { name: String },
+    /// Print a wallet's seed phrase, or the secret key of a single-key wallet
+    Export { name: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum TxCommand {
+    /// Sign a payment from one of your wallets
+    Send {
+        /// Wallet to pay from
+        #[arg(long)]
+        from: String,
+        /// Address to pay
+        #[arg(long)]
+        to: Address,
+        /// Coins to send
+        #[arg(long, value_parser = parse_coins)]
+        amount: u64,
+        /// Coins left for the miner
+        #[arg(long, value_parser = parse_coins, default_value = "0")]
+        fee: u64,
+        /// Also write the transaction to this file
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// List the transactions waiting in the mempool
+    List,
+}
+
+/// Apply the platform policy from the command line, environment or config, in that order.
+fn check_platform(args: &Args, config: &Config, platform: &Platform) -> mchain::Result<()> {
+    let policy = match args.platform_policy {
+        Some(policy) => policy,
+        None => config.platform_policy()?.unwrap_or_default(),
+    };
+    match policy.evaluate(platform) {
+        Verdict::Allowed => {}
+        Verdict::Warn => println!("⚠️ {} is not Apple Silicon; continuing under the `warn` platform policy.", platform),
+        Verdict::Denied => {
+            println!("🚫 MChain only runs on Apple Silicon (detected {}).", platform);
+            println!("   Use --platform-policy warn|any or MCHAIN_PLATFORM_POLICY to run elsewhere.");
+            exit(1);
         }
-        nonce += 1;
     }
+    Ok(())
 }
 
-fn save_block_to_file(block: &Block) {
-    let dir = Path::new("mchain_data");
-    if !dir.exists() {
-        fs::create_dir_all(dir).expect("Failed to create mchain_data");
-    }
-    let path = format!("mchain_data/block_{}.json", block.index);
-    let json = serde_json::to_string_pretty(block).expect("Serialize fail");
-    let mut file = File::create(path).expect("File write fail");
-    file.write_all(json.as_bytes()).expect("Write fail");
+/// Difficulty in bits from `--bits` or `--difficulty` (hex digits), if either was given.
+fn difficulty_bits(bits: Option<usize>, hex_digits: Option<usize>) -> Option<usize> {
+    bits.or(hex_digits.map(|d| d * 4))
 }
 
-fn load_blocks_from_disk() -> Vec<Block> {
-    let mut chain = Vec::new();
-    let path = Path::new("mchain_data");
-    if !path.exists() {
-        return chain;
+fn print_mined(mined: &Mined) {
+    let block = &mined.block;
+    println!("✅ Block {} mined in {} ms! Nonce: {}, Hash: {}", block.index, block.mining_duration_ms, block.nonce, block.hash);
+    println!("   {} hashes at {}", mined.hashes, format_hash_rate(mined.hash_rate()));
+    let coinbase = block.body.transactions().and_then(|txs| txs.first()).filter(|tx| tx.is_coinbase());
+    for output in coinbase.map(|tx| tx.outputs.as_slice()).unwrap_or_default() {
+        println!("   💰 {} paid to {}", format_coins(output.amount), output.address);
     }
+}
 
-    let mut files: Vec<_> = fs::read_dir(path)
-        .expect("Read dir fail")
-        .filter_map(Result::ok)
-        .filter(|f| f.path().extension().map(|e| e == "json").unwrap_or(false))
-        .collect();
-
-    files.sort_by_key(|f| f.path());
+fn print_summary(blocks: u64, hashes: u64, elapsed: Duration) {
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 { hashes as f64 / secs } else { 0.0 };
+    println!("📊 Mined {} block(s) in {:.1} s: {} hashes at {}", blocks, secs, hashes, format_hash_rate(rate));
+}
 
-    for file in files {
-        let reader = BufReader::new(File::open(file.path()).expect("Open fail"));
-        if let Ok(block) = serde_json::from_reader(reader) {
-            chain.push(block);
+/// Ctrl-C once stops after the block being mined; twice abandons it. Returns the two
+/// flags, in that order.
+fn handle_interrupts() -> mchain::Result<(Arc<AtomicBool>, Arc<AtomicBool>)> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (stop_handler, cancel_handler) = (stop.clone(), cancel.clone());
+    ctrlc::set_handler(move || {
+        if stop_handler.swap(true, Ordering::SeqCst) {
+            cancel_handler.store(true, Ordering::SeqCst);
+        } else {
+            println!("\n⏸️ Finishing the current block; press Ctrl-C again to abandon it.");
         }
+    })
+    .map_err(io::Error::other)?;
+    Ok((stop, cancel))
+}
+
+/// The wallet password from `MCHAIN_WALLET_PASSWORD`, or else asked for on the terminal
+/// (twice if `confirm`).
+fn read_password(confirm: bool) -> mchain::Result<String> {
+    if let Ok(password) = std::env::var("MCHAIN_WALLET_PASSWORD") {
+        return Ok(password);
+    }
+    let password = rpassword::prompt_password("🔑 Password: ")?;
+    if confirm && rpassword::prompt_password("🔑 Repeat password: ")? != password {
+        return Err(Error::Wallet("passwords do not match".to_string()));
     }
+    Ok(password)
+}
 
-    chain
+/// The seed phrase from `MCHAIN_WALLET_MNEMONIC`, or else asked for on the terminal.
+fn read_mnemonic() -> mchain::Result<Mnemonic> {
+    let phrase = match std::env::var("MCHAIN_WALLET_MNEMONIC") {
+        Ok(phrase) => phrase,
+        Err(_) => rpassword::prompt_password("🌱 Seed phrase: ")?,
+    };
+    phrase.parse().map_err(Error::Wallet)
 }
 
-fn verify_chain(blockchain: &[Block]) -> bool {
-    for i in 1..blockchain.len() {
-        if blockchain[i].previous_hash != blockchain[i - 1].hash {
-            println!("❌ Invalid block link at index {}", i);
-            return false;
-        }
+/// The chain's unspent outputs, brought up to date with the stored blocks and saved if
+/// that took any.
+fn utxo_set(chain_dir: &Path, store: &dyn BlockStore, persistent: bool) -> mchain::Result<UtxoSet> {
+    let mut utxos = UtxoSet::load(chain_dir)?.unwrap_or_default();
+    if utxos.catch_up(store)? > 0 && persistent {
+        utxos.save(chain_dir)?;
     }
-    println!("✅ All blocks are properly linked.");
-    true
+    Ok(utxos)
 }
 
-fn list_blocks(blockchain: &[Block]) {
-    for block in blockchain {
-        println!("Block {} | Time: {}ms | Nonce: {} | Hash: {}", block.index, block.mining_duration_ms, block.nonce, block.hash);
+/// The wallet `name`, or an error saying there is none.
+fn load_wallet(data_dir: &Path, name: &str) -> mchain::Result<Keystore> {
+    Keystore::load(data_dir, name)?.ok_or_else(|| Error::Wallet(format!("no wallet named {}", name)))
+}
+
+fn format_hash_rate(rate: f64) -> String {
+    match rate {
+        r if r >= 1e9 => format!("{:.2} GH/s", r / 1e9),
+        r if r >= 1e6 => format!("{:.2} MH/s", r / 1e6),
+        r if r >= 1e3 => format!("{:.2} kH/s", r / 1e3),
+        r => format!("{:.0} H/s", r),
     }
 }
 
-fn delete_all_blocks() {
-    if Path::new("mchain_data").exists() {
-        fs::remove_dir_all("mchain_data").expect("Failed to delete mchain_data");
-        println!("🗑️ All blocks deleted.");
-    } else {
-        println!("No blocks to delete.");
+fn list_blocks(blockchain: &[Block]) {
+    for block in blockchain {
+        let difficulty = block.difficulty_bits().map(|d| format!("{} bits", d)).unwrap_or_else(|| "-".to_string());
+        let txs = block.body.transactions().map(|txs| txs.len().to_string()).unwrap_or_else(|| "-".to_string());
+        println!("Block {} | Time: {}ms | Difficulty: {} | Txs: {} | Nonce: {} | Hash: {}", block.index, block.mining_duration_ms, difficulty, txs, block.nonce, block.hash);
     }
 }
 
-fn main() {
-    if !is_apple_silicon() {
-        println!("🚫 MChain only runs on Apple Silicon.");
-        exit(1);
+/// Load the stored blocks. In strict mode any bad file is an error; otherwise each
+/// problem is printed as a warning and the blocks that did load are returned.
+fn load_blocks(store: &dyn BlockStore, strict: bool) -> mchain::Result<Vec<Block>> {
+    if strict {
+        return store.load();
+    }
+    let report = store.scan()?;
+    for issue in &report.issues {
+        println!("⚠️ {}", issue);
+    }
+    if !report.is_clean() {
+        println!("   Run `mchain repair` to quarantine bad files.");
     }
+    Ok(report.blocks)
+}
 
-    let args = Args::parse();
+fn run(args: Args, config: &Config, platform: &Platform) -> mchain::Result<()> {
+    let data_dir = args
+        .data_dir
+        .or_else(|| config.data_dir.clone())
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_DIR));
+    let chain_dir = store::chain_dir(&data_dir, args.chain.as_deref())?;
+    let backend = store::resolve_backend(&chain_dir, args.store)?;
+    let mut store = backend.open(&chain_dir)?;
+    let chain_config = ChainConfig::load(&chain_dir)?.unwrap_or_default();
+    let persistent = backend != Backend::Memory;
     match args.command {
-        Some(Commands::Mine { blocks, difficulty, data }) => {
-            let mut blockchain = load_blocks_from_disk();
+        Some(Commands::Mine {
+            blocks,
+            difficulty,
+            bits,
+            data,
+            threads,
+            retarget_interval,
+            target_block_secs,
+            pow,
+            miner_address,
+            block_reward,
+            halving_interval,
+            txs,
+            max_block_size,
+            resume,
+        }) => {
+            // Never build on a store with bad files: that would fork it.
+            let stored = store.load().inspect_err(|_| println!("🚫 Refusing to mine; run `mchain repair` first."))?;
+            let mut chain = Chain::new(stored);
 
-            if blockchain.is_empty() {
-                println!("⛏️ Creating genesis block...");
-                let genesis = mine_block(0, "Genesis Block", "0", difficulty);
-                save_block_to_file(&genesis);
-                blockchain.push(genesis);
+            let mut session = match (resume, Session::load(&chain_dir)?) {
+                (true, Some(session)) => session,
+                (true, None) => {
+                    println!("📂 No mining session to resume.");
+                    return Ok(());
+                }
+                (false, saved) => {
+                    if let Some(saved) = saved {
+                        let left = saved.remaining(chain.next_index());
+                        if left > 0 {
+                            println!("⚠️ Replacing an unfinished mining session with {} of {} blocks left.", left, saved.target_blocks);
+                        }
+                    }
+                    let difficulty = difficulty_bits(bits, difficulty).unwrap_or(DEFAULT_BITS);
+                    // The genesis block doesn't count towards `--blocks`.
+                    Session { miner_address: miner_address.map(|a| a.to_string()), ..Session::new(blocks, chain.next_index().max(1), data, difficulty) }
+                }
+            };
+
+            let (stop, cancel) = handle_interrupts()?;
+            let mut miner = Miner::new(session.difficulty).with_platform(platform.to_string()).with_cancel(cancel);
+            if let Some(threads) = threads {
+                miner = miner.with_threads(threads);
+            }
+
+            let mut consensus = Consensus {
+                min_difficulty: session.difficulty,
+                retarget: chain_config.retarget.clone(),
+                pow: chain_config.pow,
+                reward: chain_config.reward.clone(),
+            };
+            if chain.is_empty() && !resume {
+                let default = Reward::default();
+                let retarget = retarget_interval.map(|n| Retarget::new(session.difficulty, n, target_block_secs));
+                let min_difficulty = retarget.is_none().then_some(session.difficulty);
+                let new_config = ChainConfig {
+                    backend,
+                    retarget,
+                    min_difficulty,
+                    pow: pow.unwrap_or_default(),
+                    reward: Some(Reward {
+                        initial_subsidy: block_reward.unwrap_or(default.initial_subsidy),
+                        halving_interval: halving_interval.unwrap_or(default.halving_interval).max(1),
+                    }),
+                };
+                if persistent {
+                    new_config.save(&chain_dir)?;
+                }
+                consensus.retarget = new_config.retarget;
+                consensus.pow = new_config.pow;
+                consensus.reward = new_config.reward;
+            } else {
+                if retarget_interval.is_some() {
+                    println!("⚠️ --retarget-interval only applies when creating a chain; ignoring it.");
+                }
+                if pow.is_some_and(|p| p != consensus.pow) {
+                    println!("⚠️ --pow only applies when creating a chain; this one uses {}.", consensus.pow);
+                }
+                if block_reward.is_some() || halving_interval.is_some() {
+                    println!("⚠️ --block-reward and --halving-interval only apply when creating a chain; ignoring them.");
+                }
             }
+            match (&consensus.reward, &session.miner_address) {
+                (Some(_), None) => println!("⚠️ No --miner-address given; block rewards will go unclaimed."),
+                (None, Some(_)) => println!("⚠️ This chain was created without block rewards; ignoring --miner-address."),
+                _ => {}
+            }
+            // Check added transactions up front rather than mine an invalid block.
+            let mut utxos = utxo_set(&chain_dir, store.as_ref(), persistent)?;
+            let mut mempool = Mempool::load(&chain_dir)?.unwrap_or_default();
+            let dropped = mempool.prune(&utxos).len();
+            if dropped > 0 {
+                println!("🗑️ Dropped {} pending transaction(s) that no longer apply.", dropped);
+            }
+            let mut added = 0;
+            for path in &txs {
+                let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+                if mempool.contains(&tx.id()) {
+                    println!("📬 {} is already in the mempool.", path.display());
+                    continue;
+                }
+                if let Err(reasons) = mempool.add(tx, &utxos) {
+                    for reason in reasons {
+                        println!("❌ {}: {}", path.display(), reason);
+                    }
+                    println!("🚫 Refusing to mine an invalid transaction.");
+                    exit(1);
+                }
+                added += 1;
+            }
+            if persistent {
+                if dropped > 0 || added > 0 {
+                    mempool.save(&chain_dir)?;
+                }
+                session.save(&chain_dir)?;
+            }
+            miner = miner.with_pow(consensus.pow);
+            println!("⛏️ Mining with {} thread(s) using {}", miner.threads(), miner.pow());
 
-            let mut next_index = blockchain.last().unwrap().index + 1;
+            let started = Instant::now();
+            let (mut mined_blocks, mut hashes) = (0, 0);
+            while chain.is_empty() || session.remaining(chain.next_index()) > 0 {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let index = chain.next_index();
+                if let Some(d) = chain.next_difficulty(&consensus) {
+                    if d != miner.difficulty() && !chain.is_empty() {
+                        println!("🎯 Difficulty retargeted to {} bits", d);
+                    }
+                    miner.set_difficulty(d);
+                }
+                let payload = if chain.is_empty() {
+                    println!("⛏️ Creating genesis block...");
+                    GENESIS_DATA.to_string()
+                } else {
+                    session.payload(index)
+                };
+                let miner_address = session.miner_address.as_deref();
+                let data = Transaction::data(payload);
+                let reserved = data.encode().len() + consensus.reward.as_ref().map_or(0, |r| r.coinbase(index, 0, miner_address).encode().len());
+                let (picked, fees) = mempool.select(&utxos, max_block_size.saturating_sub(reserved));
+                let included = picked.len();
+                let mut transactions = Vec::new();
+                if let Some(reward) = &consensus.reward {
+                    transactions.push(reward.coinbase(index, fees, miner_address));
+                }
+                transactions.extend(picked);
+                transactions.push(data);
+                match chain.resume_next(&miner, transactions, session.progress_for(index)) {
+                    Ok(mined) => {
+                        print_mined(&mined);
+                        store.append(&mined.block)?;
+                        utxos.apply_block(&mined.block);
+                        let removed = mempool.remove_included(&mined.block, &utxos);
+                        if persistent {
+                            utxos.save(&chain_dir)?;
+                            if removed > 0 {
+                                mempool.save(&chain_dir)?;
+                            }
+                        }
+                        if included > 0 {
+                            println!("   📥 Included {} transaction(s) from the mempool", included);
+                        }
+                        mined_blocks += 1;
+                        hashes += mined.hashes;
+                    }
+                    Err(Error::Cancelled { progress, hashes: tried }) => {
+                        println!("⏹️ Abandoned block {} after {} hashes.", index, tried);
+                        hashes += tried;
+                        session.next_index = index;
+                        session.progress = progress;
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
 
-            for _ in 0..blocks {
-                let prev_hash = &blockchain.last().unwrap().hash;
-                let block = mine_block(next_index, &format!("{} #{}", data, next_index), prev_hash, difficulty);
-                save_block_to_file(&block);
-                blockchain.push(block);
-                next_index += 1;
+            print_summary(mined_blocks, hashes, started.elapsed());
+            let left = session.remaining(chain.next_index());
+            if persistent && left == 0 && !chain.is_empty() {
+                Session::remove(&chain_dir)?;
+            } else if persistent {
+                session.save(&chain_dir)?;
+                println!("💾 {} of {} blocks left; run `mchain mine --resume` to continue.", left, session.target_blocks);
             }
         },
-        Some(Commands::Verify) => {
-            let chain = load_blocks_from_disk();
+        Some(Commands::Verify { difficulty, bits }) => {
+            let chain = Chain::new(load_blocks(store.as_ref(), args.strict)?);
             if chain.is_empty() {
                 println!("📂 No blocks found.");
             } else {
-                verify_chain(&chain);
+                let min_difficulty = match (difficulty_bits(bits, difficulty), &chain_config.retarget) {
+                    (Some(d), _) => d,
+                    (None, Some(_)) => 1,
+                    // Chains created before the floor was recorded are held to their first
+                    // block's difficulty, and legacy blocks that record none to the default.
+                    (None, None) => chain_config
+                        .min_difficulty
+                        .or_else(|| chain.blocks()[0].difficulty_bits())
+                        .unwrap_or(DEFAULT_BITS),
+                };
+                let consensus = Consensus {
+                    min_difficulty,
+                    retarget: chain_config.retarget.clone(),
+                    pow: chain_config.pow,
+                    reward: chain_config.reward.clone(),
+                };
+                let violations = chain.verify(&consensus);
+                if violations.is_empty() {
+                    println!("✅ All {} blocks are valid.", chain.len());
+                } else {
+                    for violation in &violations {
+                        println!("❌ {}", violation);
+                    }
+                    println!("🚫 {} violation(s) found.", violations.len());
+                    exit(1);
+                }
             }
         },
         Some(Commands::List) => {
-            let chain = load_blocks_from_disk();
+            let chain = load_blocks(store.as_ref(), args.strict)?;
             if chain.is_empty() {
                 println!("📂 No blocks found.");
             } else {
                 list_blocks(&chain);
             }
         },
+        Some(Commands::Repair) => {
+            let moved = store.repair()?;
+            if moved.is_empty() {
+                println!("✅ Nothing to repair.");
+            }
+            for (from, to) in &moved {
+                println!("📦 {} -> {}", from.display(), to.display());
+            }
+        },
         Some(Commands::Reset) => {
-            delete_all_blocks();
+            if store::reset_chain(&chain_dir, store.as_mut())? {
+                println!("🗑️ All blocks deleted.");
+            } else {
+                println!("No blocks to delete.");
+            }
+        },
+        Some(Commands::Chains) => {
+            let names = store::chain_names(&data_dir)?;
+            if names.is_empty() {
+                println!("📂 No named chains in {}.", data_dir.display());
+            }
+            for name in names {
+                let dir = store::chain_dir(&data_dir, Some(&name))?;
+                let config = ChainConfig::load(&dir)?.unwrap_or_default();
+                let count = config.backend.open(&dir)?.len()?;
+                println!("⛓️ {} ({} blocks, {} store, {})", name, count, config.backend, config.pow);
+            }
+        },
+        Some(Commands::Migrate { to }) => {
+            let moved = store::migrate(&chain_dir, to)?;
+            println!("🚚 Moved {} blocks to the {} backend.", moved, to);
+        },
+        Some(Commands::Prove { block, item, checkpoint, out }) => {
+            let blocks = store.load()?;
+            let proof = InclusionProof::build(&blocks, chain_config.pow, block, item, checkpoint)?;
+            let json = serde_json::to_string_pretty(&proof)?;
+            match out {
+                Some(path) => {
+                    fs::write(&path, json)?;
+                    println!("🧾 Proof of block {} item {} written to {}.", block, item, path.display());
+                }
+                None => println!("{}", json),
+            }
+        },
+        Some(Commands::VerifyProof { proof, checkpoint_hash }) => {
+            let proof: InclusionProof = serde_json::from_str(&fs::read_to_string(&proof)?)?;
+            match proof.verify(checkpoint_hash.as_deref()) {
+                Ok((checkpoint, hash)) => {
+                    println!("✅ Transaction {} is item {} of block {}.", proof.transaction.id_hex(), proof.item, proof.headers[0].index);
+                    println!("   Anchored to block {} with hash {}.", checkpoint.index, hash);
+                    if checkpoint_hash.is_none() {
+                        println!("   Compare that hash with a trusted copy of the chain, or pass --checkpoint-hash.");
+                    }
+                }
+                Err(e) => {
+                    println!("❌ {}", e);
+                    exit(1);
+                }
+            }
+        },
+        Some(Commands::Wallet { command }) => match command {
+            WalletCommand::New { name } => {
+                if Keystore::load(&data_dir, &name)?.is_some() {
+                    return Err(Error::Wallet(format!("wallet {} already exists", name)));
+                }
+                let password = read_password(true)?;
+                if password.is_empty() {
+                    return Err(Error::Wallet("the password must not be empty".to_string()));
+                }
+                let mnemonic = Mnemonic::generate()?;
+                let keystore = Keystore::seal_hd(&name, &mnemonic, 0, 1, &password)?;
+                keystore.save(&data_dir)?;
+                println!("👛 Created wallet {} with address {}", name, keystore.address);
+                println!("🌱 Write down this seed phrase; it restores every address of the wallet:");
+                println!("{}", mnemonic);
+            }
+            WalletCommand::Restore { name, account } => {
+                if Keystore::load(&data_dir, &name)?.is_some() {
+                    return Err(Error::Wallet(format!("wallet {} already exists", name)));
+                }
+                let mnemonic = read_mnemonic()?;
+                let password = read_password(true)?;
+                if password.is_empty() {
+                    return Err(Error::Wallet("the password must not be empty".to_string()));
+                }
+                let blocks = load_blocks(store.as_ref(), args.strict)?;
+                let used: HashSet<&str> = blocks
+                    .iter()
+                    .flat_map(|block| block.body.transactions().unwrap_or_default())
+                    .flat_map(|tx| tx.outputs.iter().map(|output| output.address.as_str()))
+                    .collect();
+                let keystore = Keystore::restore(&name, &mnemonic, account, &password, |address| used.contains(address))?;
+                keystore.save(&data_dir)?;
+                let addresses = keystore.addresses();
+                let utxos = utxo_set(&chain_dir, store.as_ref(), persistent)?;
+                let balance = addresses.iter().fold(0u64, |total, address| total.saturating_add(utxos.balance(address)));
+                println!("♻️ Restored wallet {} with {} address(es) from {} blocks.", name, addresses.len(), blocks.len());
+                println!("💰 Balance: {}", format_coins(balance));
+            }
+            WalletCommand::Address { name } => {
+                let mut wallet = load_wallet(&data_dir, &name)?;
+                let keypair = wallet.derive_next(&read_password(false)?)?;
+                wallet.update(&data_dir)?;
+                println!("📫 New address for {}: {}", name, keypair.address());
+            }
+            WalletCommand::List => {
+                let wallets = Keystore::list(&data_dir)?;
+                if wallets.is_empty() {
+                    println!("📂 No wallets in {}.", data_dir.display());
+                }
+                for wallet in wallets {
+                    println!("👛 {} {}", wallet.name, wallet.address);
+                }
+            }
+            WalletCommand::Show { name } => {
+                let wallet = load_wallet(&data_dir, &name)?;
+                println!("👛 {}", wallet.name);
+                match &wallet.hd {
+                    Some(hd) => {
+                        for (index, key) in hd.keys.iter().enumerate() {
+                            println!("   {} {} ({})", hd::path_string(hd.account, index as u32), key.address, key.public_key);
+                        }
+                    }
+                    None => {
+                        println!("   Address: {}", wallet.address);
+                        println!("   Public key: {}", wallet.public_key);
+                    }
+                }
+            }
+            WalletCommand::Export { name } => {
+                let wallet = load_wallet(&data_dir, &name)?;
+                let password = read_password(false)?;
+                if let Some(mnemonic) = wallet.mnemonic(&password)? {
+                    println!("⚠️ Anyone with this seed phrase can spend from every address of {}.", name);
+                    println!("{}", mnemonic);
+                } else {
+                    let keypair = wallet.unlock(&password)?;
+                    println!("⚠️ Anyone with this key can spend from {}.", keypair.address());
+                    println!("{}", keypair.secret().iter().map(|b| format!("{:02x}", b)).collect::<String>());
+                }
+            }
+        },
+        Some(Commands::Tx { command: TxCommand::Send { from, to, amount, fee, out } }) => {
+            let utxos = utxo_set(&chain_dir, store.as_ref(), persistent)?;
+            let mut mempool = Mempool::load(&chain_dir)?.unwrap_or_default();
+            let keys = load_wallet(&data_dir, &from)?.unlock_all(&read_password(false)?)?;
+            // Leave alone the outputs pending transactions already spend.
+            let tx = mempool.spendable(&utxos).pay_from(&keys, &to.to_string(), amount, fee)?;
+            if let Err(reasons) = mempool.add(tx.clone(), &utxos) {
+                let reasons: Vec<_> = reasons.iter().map(ToString::to_string).collect();
+                return Err(Error::Wallet(reasons.join("; ")));
+            }
+            if persistent {
+                mempool.save(&chain_dir)?;
+            }
+            println!("✍️ Signed transaction {} paying {} to {} (fee {})", tx.id_hex(), format_coins(amount), to, format_coins(fee));
+            println!("   📬 Added to the mempool ({} pending); `mchain mine` includes it in the next block.", mempool.len());
+            if let Some(path) = out {
+                fs::write(&path, serde_json::to_string_pretty(&tx)?)?;
+                println!("   Also written to {}.", path.display());
+            }
+        },
+        Some(Commands::Tx { command: TxCommand::List }) => {
+            let mempool = Mempool::load(&chain_dir)?.unwrap_or_default();
+            if mempool.is_empty() {
+                println!("📭 No pending transactions.");
+            }
+            let fees = mempool.fees(&utxo_set(&chain_dir, store.as_ref(), persistent)?);
+            for (tx, fee) in mempool.transactions().iter().zip(fees) {
+                let paid = tx.output_total().unwrap_or(u64::MAX);
+                println!("📬 {} pays {} in {} output(s), fee {}", tx.id_hex(), format_coins(paid), tx.outputs.len(), format_coins(fee));
+            }
+        },
+        Some(Commands::Balance { address, wallet }) => {
+            let utxos = utxo_set(&chain_dir, store.as_ref(), persistent)?;
+            let (owner, addresses) = match (address, wallet) {
+                (Some(address), _) => (address.to_string(), vec![address.to_string()]),
+                (None, Some(name)) => {
+                    let wallet = load_wallet(&data_dir, &name)?;
+                    (name, wallet.addresses().into_iter().map(String::from).collect())
+                }
+                (None, None) => unreachable!("clap requires an address or a wallet"),
+            };
+            let balance = addresses.iter().fold(0u64, |total, address| total.saturating_add(utxos.balance(address)));
+            let count: usize = addresses.iter().map(|address| utxos.owned_by(address).count()).sum();
+            println!("💰 {} has {} in {} unspent output(s)", owner, format_coins(balance), count);
+            if let Some(tip) = utxos.tip() {
+                println!("   As of block {} ({})", utxos.height() - 1, tip);
+            }
+        },
+        Some(Commands::Reindex) => {
+            let utxos = UtxoSet::from_blocks(&store.load()?);
+            if persistent {
+                utxos.save(&chain_dir)?;
+            }
+            println!("🗂️ Rebuilt the unspent output set from {} blocks: {} unspent output(s).", utxos.height(), utxos.len());
+        },
+        Some(Commands::Rewind { height }) => {
+            let orphaned = match height.checked_add(1) {
+                Some(len) => store.truncate(len)?,
+                None => Vec::new(),
+            };
+            if orphaned.is_empty() {
+                println!("✅ No blocks above {}.", height);
+            } else {
+                let utxos = utxo_set(&chain_dir, store.as_ref(), persistent)?;
+                let mut mempool = Mempool::load(&chain_dir)?.unwrap_or_default();
+                let returned = mempool.readmit(&orphaned, &utxos);
+                if persistent {
+                    mempool.save(&chain_dir)?;
+                }
+                println!("⏪ Orphaned {} block(s) above {}; {} transaction(s) returned to the mempool.", orphaned.len(), height, returned);
+            }
         },
         None => {
             println!("Use --help to see available commands.");
         }
     }
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    let platform = Platform::detect();
+    let result = Config::load(&args.config).and_then(|config| {
+        check_platform(&args, &config, &platform)?;
+        run(args, &config, &platform)
+    });
+    if let Err(e) = result {
+        eprintln!("❌ {}", e);
+        exit(1);
+    }
 }
@@ -0,0 +1,184 @@
+//! Transactions waiting to be mined.
+//!
+//! `tx send` adds to the chain's `mempool.json` and `mine` fills each block from it,
+//! highest fee first, until the block is full. A transaction leaves the pool once a
+//! stored block includes it, or once a block spends one of its inputs some other way.
+//! Transactions from orphaned blocks are put back.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::Block;
+use crate::error::Result;
+use crate::store::{self, MEMPOOL_FILE};
+use crate::transaction::{OutPoint, Transaction};
+use crate::utxo::UtxoSet;
+use crate::verify::{check_transaction, Reason};
+
+/// Size limit of a block template, in bytes of encoded transactions.
+pub const DEFAULT_MAX_BLOCK_BYTES: usize = 1_000_000;
+
+/// Pending transactions, in the order they arrived. Each one is valid once the ones
+/// before it are applied to the chain's unspent outputs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mempool {
+    transactions: Vec<Transaction>,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Mempool::default()
+    }
+
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    pub fn contains(&self, txid: &[u8; 32]) -> bool {
+        self.transactions.iter().any(|tx| &tx.id() == txid)
+    }
+
+    /// `utxos` with every pending transaction applied: what a new transaction may spend.
+    pub fn spendable(&self, utxos: &UtxoSet) -> UtxoSet {
+        let mut spendable = utxos.clone();
+        for tx in &self.transactions {
+            spendable.apply(tx);
+        }
+        spendable
+    }
+
+    /// Add `tx` if it is valid on top of `utxos` and the transactions already pending,
+    /// so it can't spend an output another pending transaction spends. Returns its fee,
+    /// or every reason it is invalid.
+    pub fn add(&mut self, tx: Transaction, utxos: &UtxoSet) -> std::result::Result<u64, Vec<Reason>> {
+        let fee = check_transaction(&self.spendable(utxos), self.len(), &tx)?;
+        self.transactions.push(tx);
+        Ok(fee)
+    }
+
+    /// Fee each pending transaction leaves, with inputs looked up in `utxos` and in the
+    /// outputs of the other pending transactions.
+    pub fn fees(&self, utxos: &UtxoSet) -> Vec<u64> {
+        let mut pending = HashMap::new();
+        for tx in &self.transactions {
+            let txid = tx.id();
+            for (vout, output) in tx.outputs.iter().enumerate() {
+                pending.insert(OutPoint { txid, vout: vout as u32 }, output.amount);
+            }
+        }
+        self.transactions
+            .iter()
+            .map(|tx| {
+                let available = tx
+                    .outpoints()
+                    .map(|outpoint| utxos.get(&outpoint).map(|o| o.amount).or(pending.get(&outpoint).copied()).unwrap_or(0))
+                    .fold(0u64, u64::saturating_add);
+                available.saturating_sub(tx.output_total().unwrap_or(u64::MAX))
+            })
+            .collect()
+    }
+
+    /// Transactions for a block on top of `utxos` whose encodings add up to at most
+    /// `max_bytes`, highest fee first, and the fees they leave. A transaction spending
+    /// another pending one's output is only picked after it.
+    pub fn select(&self, utxos: &UtxoSet, max_bytes: usize) -> (Vec<Transaction>, u64) {
+        let fees = self.fees(utxos);
+        let mut candidates: Vec<usize> = (0..self.len()).collect();
+        // Stable, so equal fees keep their arrival order.
+        candidates.sort_by_key(|&i| std::cmp::Reverse(fees[i]));
+
+        let mut working = utxos.clone();
+        let (mut picked, mut total_fees, mut used) = (Vec::new(), 0u64, 0);
+        let mut taken = HashSet::new();
+        loop {
+            let before = picked.len();
+            for &i in &candidates {
+                let tx = &self.transactions[i];
+                let size = tx.encode().len();
+                if taken.contains(&i) || used + size > max_bytes {
+                    continue;
+                }
+                if let Ok(fee) = check_transaction(&working, picked.len(), tx) {
+                    working.apply(tx);
+                    taken.insert(i);
+                    picked.push(tx.clone());
+                    total_fees = total_fees.saturating_add(fee);
+                    used += size;
+                }
+            }
+            // Another pass may pick up transactions whose inputs the last one created.
+            if picked.len() == before {
+                return (picked, total_fees);
+            }
+        }
+    }
+
+    /// Drop the transactions `block` includes, then any that conflict with it, given the
+    /// unspent outputs `utxos` after it. Returns how many were dropped.
+    pub fn remove_included(&mut self, block: &Block, utxos: &UtxoSet) -> usize {
+        let included: HashSet<_> = block.body.transactions().unwrap_or_default().iter().map(Transaction::id).collect();
+        let before = self.len();
+        self.transactions.retain(|tx| !included.contains(&tx.id()));
+        self.prune(utxos);
+        before - self.len()
+    }
+
+    /// Drop every transaction that is no longer valid on top of `utxos` and the ones
+    /// kept before it. Returns the dropped ones.
+    pub fn prune(&mut self, utxos: &UtxoSet) -> Vec<Transaction> {
+        let mut working = utxos.clone();
+        let (mut kept, mut dropped) = (Vec::new(), Vec::new());
+        for tx in self.transactions.drain(..) {
+            if check_transaction(&working, kept.len(), &tx).is_ok() {
+                working.apply(&tx);
+                kept.push(tx);
+            } else {
+                dropped.push(tx);
+            }
+        }
+        self.transactions = kept;
+        dropped
+    }
+
+    /// Put back the transfers from `orphaned` blocks, ahead of the transactions already
+    /// pending, then drop whatever no longer fits on `utxos`. Coinbases and data-only
+    /// transactions are left out: they belong to the block that carried them. Returns how
+    /// many transfers are pending again.
+    pub fn readmit(&mut self, orphaned: &[Block], utxos: &UtxoSet) -> usize {
+        let mut returned: Vec<Transaction> = orphaned
+            .iter()
+            .flat_map(|block| block.body.transactions().unwrap_or_default())
+            .filter(|tx| !tx.is_coinbase() && !tx.inputs.is_empty())
+            .filter(|tx| !self.contains(&tx.id()))
+            .cloned()
+            .collect();
+        let ids: HashSet<_> = returned.iter().map(Transaction::id).collect();
+        returned.append(&mut self.transactions);
+        self.transactions = returned;
+        self.prune(utxos);
+        self.transactions.iter().filter(|tx| ids.contains(&tx.id())).count()
+    }
+
+    /// The pool saved for the chain in `dir`; `None` until a transaction is first sent.
+    pub fn load(dir: &Path) -> Result<Option<Mempool>> {
+        store::read_side_file(dir, MEMPOOL_FILE)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        store::write_side_file(dir, MEMPOOL_FILE, self)
+    }
+
+    pub fn remove(dir: &Path) -> Result<()> {
+        store::remove_side_file(dir, MEMPOOL_FILE)
+    }
+}
@@ -0,0 +1,96 @@
+//! Merkle trees over a block's transactions.
+//!
+//! The tree follows RFC 6962 (Certificate Transparency): leaves are hashed as
+//! `SHA-256(0x00 || encoding)` and inner nodes as `SHA-256(0x01 || left || right)`, so a
+//! leaf can never be passed off as a node. A list of `n` leaves splits at the largest
+//! power of two below `n` rather than duplicating the last leaf, which keeps two
+//! different transaction lists from sharing a root. The empty tree's root is
+//! `SHA-256("")`.
+
+use sha2::{Digest, Sha256};
+
+use crate::transaction::Transaction;
+
+/// Hash of one leaf.
+pub fn leaf_hash(encoding: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(encoding);
+    hasher.finalize().into()
+}
+
+/// Hash of an inner node.
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Root over already hashed leaves.
+pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    match leaves.len() {
+        0 => Sha256::digest([]).into(),
+        1 => leaves[0],
+        n => {
+            let (left, right) = leaves.split_at(split(n));
+            node_hash(&root(left), &root(right))
+        }
+    }
+}
+
+/// Root over `transactions`, in order.
+pub fn transactions_root(transactions: &[Transaction]) -> [u8; 32] {
+    let leaves: Vec<_> = transactions.iter().map(|tx| leaf_hash(&tx.encode())).collect();
+    root(&leaves)
+}
+
+/// Largest power of two strictly below `n`, for `n >= 2`.
+fn split(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+/// Audit path for the leaf at `index`: the sibling hashes from the leaf up to the root.
+/// Empty if `index` is out of range.
+pub fn proof(leaves: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+    if index >= leaves.len() || leaves.len() == 1 {
+        return Vec::new();
+    }
+    let (left, right) = leaves.split_at(split(leaves.len()));
+    let (mut path, sibling) = if index < left.len() {
+        (proof(left, index), root(right))
+    } else {
+        (proof(right, index - left.len()), root(left))
+    };
+    path.push(sibling);
+    path
+}
+
+/// Root implied by `leaf` sitting at `index` in a tree of `size` leaves with audit path
+/// `path`, or `None` if the path has the wrong shape for that position.
+pub fn root_from_proof(leaf: [u8; 32], index: usize, size: usize, path: &[[u8; 32]]) -> Option<[u8; 32]> {
+    if index >= size {
+        return None;
+    }
+    // RFC 9162, section 2.1.3.2.
+    let (mut node, mut last) = (index, size - 1);
+    let mut hash = leaf;
+    for sibling in path {
+        if last == 0 {
+            return None;
+        }
+        if node & 1 == 1 || node == last {
+            hash = node_hash(sibling, &hash);
+            while node & 1 == 0 && node != 0 {
+                node >>= 1;
+                last >>= 1;
+            }
+        } else {
+            hash = node_hash(&hash, sibling);
+        }
+        node >>= 1;
+        last >>= 1;
+    }
+    (last == 0).then_some(hash)
+}
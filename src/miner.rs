@@ -0,0 +1,247 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::{meets_difficulty, Block, Body};
+use crate::error::{Error, Result};
+use crate::header::{self, CURRENT_VERSION, EXTRA_NONCE_OFFSET, NONCE_OFFSET, TIMESTAMP_OFFSET};
+use crate::hex;
+use crate::pow::Pow;
+use crate::transaction::Transaction;
+
+/// Hashes a worker computes between looks at the clock.
+const CLOCK_CHECK_HASHES: u64 = 256;
+
+/// Proof-of-work miner for a fixed difficulty (number of leading zero bits).
+///
+/// The nonce space is split across `threads` workers: worker `w` tries nonces
+/// `w, w + threads, w + 2 * threads, ...`, and all workers stop as soon as one succeeds.
+/// Workers keep the header timestamp current while they search, so a block records
+/// roughly when it was solved, and roll the header's `extra_nonce` whenever they run
+/// out of nonces.
+#[derive(Debug, Clone)]
+pub struct Miner {
+    difficulty: usize,
+    threads: usize,
+    pow: Pow,
+    max_nonce: u64,
+    cancel: Option<Arc<AtomicBool>>,
+    platform: Option<String>,
+    clock: fn() -> u64,
+}
+
+/// How far a search got: every nonce below `nonce` under `extra_nonce` (and every nonce
+/// under lower extra nonces) has been tried.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Progress {
+    pub extra_nonce: u64,
+    pub nonce: u64,
+}
+
+/// Where a worker found a solution.
+struct Hit {
+    nonce: u64,
+    extra_nonce: u64,
+    timestamp: u64,
+    hash: String,
+}
+
+/// A freshly mined block and the work spent finding it.
+#[derive(Debug, Clone)]
+pub struct Mined {
+    pub block: Block,
+    /// Hashes computed across all workers.
+    pub hashes: u64,
+}
+
+impl Mined {
+    /// Aggregate hashes per second over the mining duration.
+    pub fn hash_rate(&self) -> f64 {
+        let secs = self.block.mining_duration_ms as f64 / 1000.0;
+        if secs > 0.0 { self.hashes as f64 / secs } else { 0.0 }
+    }
+}
+
+impl Miner {
+    /// A miner using every available core.
+    pub fn new(difficulty: usize) -> Self {
+        Miner { difficulty, threads: available_threads(), pow: Pow::default(), max_nonce: u64::MAX, cancel: None, platform: None, clock: unix_now }
+    }
+
+    /// Roll `extra_nonce` once the nonce would pass `max_nonce`, instead of at `u64::MAX`.
+    pub fn with_max_nonce(mut self, max_nonce: u64) -> Self {
+        self.max_nonce = max_nonce;
+        self
+    }
+
+    /// Hash with `pow` instead of SHA-256.
+    pub fn with_pow(mut self, pow: Pow) -> Self {
+        self.pow = pow;
+        self
+    }
+
+    /// Use `threads` workers (at least one).
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Abandon the search as soon as `cancel` is set, failing with [`Error::Cancelled`].
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Record `platform` in every mined block's metadata.
+    pub fn with_platform(mut self, platform: impl Into<String>) -> Self {
+        self.platform = Some(platform.into());
+        self
+    }
+
+    /// Read the time from `clock`, in seconds since the Unix epoch, instead of the
+    /// system clock.
+    pub fn with_clock(mut self, clock: fn() -> u64) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Change the difficulty for subsequent blocks, e.g. after a retarget.
+    pub fn set_difficulty(&mut self, difficulty: usize) {
+        self.difficulty = difficulty;
+    }
+
+    pub fn difficulty(&self) -> usize {
+        self.difficulty
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn pow(&self) -> Pow {
+        self.pow
+    }
+
+    /// Search nonces, extra nonces and timestamps until the block hash meets the
+    /// difficulty target.
+    pub fn mine(&self, index: u64, transactions: Vec<Transaction>, previous_hash: &str) -> Result<Mined> {
+        self.resume(index, transactions, previous_hash, Progress::default())
+    }
+
+    /// Like [`Miner::mine`], but skip the nonces an earlier, cancelled search for the same
+    /// block already tried.
+    pub fn resume(&self, index: u64, transactions: Vec<Transaction>, previous_hash: &str, from: Progress) -> Result<Mined> {
+        let body = Body::Transactions { transactions };
+        let mut block = Block {
+            version: CURRENT_VERSION,
+            index,
+            timestamp: (self.clock)(),
+            merkle_root: body.merkle_root(),
+            body,
+            nonce: from.nonce,
+            extra_nonce: from.extra_nonce,
+            difficulty: Some(self.difficulty),
+            previous_hash: previous_hash.to_string(),
+            hash: String::new(),
+            mining_duration_ms: 0,
+            platform: self.platform.clone(),
+        };
+        let preimage = header::preimage(&block)?;
+        let found = AtomicBool::new(false);
+        let start = Instant::now();
+
+        let results: Vec<_> = thread::scope(|s| {
+            let workers: Vec<_> = (0..self.threads)
+                .map(|w| {
+                    let mut preimage = preimage.clone();
+                    let found = &found;
+                    s.spawn(move || self.search(&mut preimage, block.timestamp, from, w as u64, found))
+                })
+                .collect();
+            workers.into_iter().map(|h| h.join().expect("mining worker panicked")).collect()
+        });
+
+        let hashes = results.iter().map(|(_, n, _)| n).sum();
+        let reached = results.iter().map(|(_, _, reached)| *reached).min().unwrap_or(from);
+        let Some(hit) = results
+            .into_iter()
+            .filter_map(|(hit, _, _)| hit)
+            .min_by_key(|hit| (hit.extra_nonce, hit.nonce))
+        else {
+            return Err(Error::Cancelled { progress: reached, hashes });
+        };
+        block.nonce = hit.nonce;
+        block.extra_nonce = hit.extra_nonce;
+        block.timestamp = hit.timestamp;
+        block.hash = hit.hash;
+        block.mining_duration_ms = start.elapsed().as_millis();
+        Ok(Mined { block, hashes })
+    }
+
+    /// One worker's stride through the nonce space from `from`, starting with a preimage
+    /// stamped `timestamp`. Returns the solution, if this worker found it, the number of
+    /// hashes computed, and how far it got, counted in whole strides so that the least
+    /// of every worker's is a safe place to resume.
+    fn search(
+        &self,
+        preimage: &mut [u8],
+        mut timestamp: u64,
+        from: Progress,
+        offset: u64,
+        found: &AtomicBool,
+    ) -> (Option<Hit>, u64, Progress) {
+        let algorithm = self.pow.algorithm();
+        let cancelled = || self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed));
+        let mut base = from.nonce;
+        let mut extra_nonce = from.extra_nonce;
+        let mut hashes = 0u64;
+        while !found.load(Ordering::Relaxed) && !cancelled() {
+            if hashes.is_multiple_of(CLOCK_CHECK_HASHES) {
+                let now = (self.clock)();
+                if now > timestamp {
+                    timestamp = now;
+                    preimage[TIMESTAMP_OFFSET..TIMESTAMP_OFFSET + 8].copy_from_slice(&timestamp.to_le_bytes());
+                }
+            }
+            // On the last stride this worker's nonce can lie past `max_nonce`; it skips
+            // straight to the next extra nonce instead.
+            if let Some(nonce) = base.checked_add(offset).filter(|&nonce| nonce <= self.max_nonce) {
+                preimage[NONCE_OFFSET..NONCE_OFFSET + 8].copy_from_slice(&nonce.to_le_bytes());
+                let digest = algorithm.hash(preimage);
+                hashes += 1;
+                if meets_difficulty(&digest, self.difficulty) {
+                    found.store(true, Ordering::Relaxed);
+                    let hash = hex::encode(&digest);
+                    return (Some(Hit { nonce, extra_nonce, timestamp, hash }), hashes, Progress { extra_nonce, nonce: base });
+                }
+            }
+            match base.checked_add(self.threads as u64).filter(|&next| next <= self.max_nonce) {
+                Some(next) => base = next,
+                None => {
+                    // Every worker rolls over at the same point, so they all move on to
+                    // the same extra nonce and keep to their own nonces within it.
+                    let Some(next) = extra_nonce.checked_add(1) else { break };
+                    extra_nonce = next;
+                    preimage[EXTRA_NONCE_OFFSET..EXTRA_NONCE_OFFSET + 8].copy_from_slice(&extra_nonce.to_le_bytes());
+                    base = 0;
+                }
+            }
+        }
+        (None, hashes, Progress { extra_nonce, nonce: base })
+    }
+}
+
+/// Number of cores available to this process.
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
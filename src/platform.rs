@@ -0,0 +1,122 @@
+//! Host CPU detection and the policy deciding which hosts may run MChain.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// What to do when the host is not Apple Silicon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlatformPolicy {
+    /// Refuse to run anywhere else.
+    #[default]
+    RequireAppleSilicon,
+    /// Print a warning and carry on.
+    Warn,
+    /// Run anywhere without comment.
+    Any,
+}
+
+/// Outcome of applying a [`PlatformPolicy`] to a [`Platform`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Allowed,
+    Warn,
+    Denied,
+}
+
+/// The detected host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Platform {
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// CPU brand string, if it could be determined.
+    pub cpu: Option<String>,
+}
+
+impl PlatformPolicy {
+    pub fn evaluate(self, platform: &Platform) -> Verdict {
+        match (self, platform.is_apple_silicon()) {
+            (_, true) | (PlatformPolicy::Any, _) => Verdict::Allowed,
+            (PlatformPolicy::Warn, false) => Verdict::Warn,
+            (PlatformPolicy::RequireAppleSilicon, false) => Verdict::Denied,
+        }
+    }
+}
+
+impl FromStr for PlatformPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "require-apple-silicon" => Ok(PlatformPolicy::RequireAppleSilicon),
+            "warn" => Ok(PlatformPolicy::Warn),
+            "any" => Ok(PlatformPolicy::Any),
+            _ => Err(format!("unknown platform policy {:?} (expected require-apple-silicon, warn or any)", s)),
+        }
+    }
+}
+
+impl fmt::Display for PlatformPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PlatformPolicy::RequireAppleSilicon => "require-apple-silicon",
+            PlatformPolicy::Warn => "warn",
+            PlatformPolicy::Any => "any",
+        })
+    }
+}
+
+impl Platform {
+    /// Detect the host. Never fails: an undetectable CPU is reported as `None`.
+    pub fn detect() -> Platform {
+        Platform {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            cpu: detect_cpu(),
+        }
+    }
+
+    pub fn is_apple_silicon(&self) -> bool {
+        self.arch == "aarch64" && self.cpu.as_deref().is_some_and(|cpu| cpu.starts_with("Apple"))
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.os, self.arch)?;
+        if let Some(cpu) = &self.cpu {
+            write!(f, " ({})", cpu)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn detect_cpu() -> Option<String> {
+    let output = std::process::Command::new("sysctl").arg("-n").arg("machdep.cpu.brand_string").output().ok()?;
+    let brand = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!brand.is_empty()).then_some(brand)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn detect_cpu() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    parse_cpuinfo(&cpuinfo)
+}
+
+/// Pull a brand out of `/proc/cpuinfo`. x86 reports `model name`; ARM only reports an
+/// implementer code, where 0x61 is Apple (e.g. Asahi Linux on an M-series Mac).
+pub fn parse_cpuinfo(cpuinfo: &str) -> Option<String> {
+    let field = |name: &str| {
+        cpuinfo.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name).then(|| value.trim().to_string())
+        })
+    };
+    if let Some(model) = field("model name") {
+        return Some(model);
+    }
+    match field("CPU implementer")?.as_str() {
+        "0x61" => Some("Apple Silicon".to_string()),
+        other => Some(format!("ARM implementer {}", other)),
+    }
+}
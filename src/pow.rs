@@ -0,0 +1,132 @@
+//! Proof-of-work hash functions.
+//!
+//! Every algorithm hashes the same header preimage (see [`header`](crate::header)) to a
+//! 32-byte digest; only the function differs. A chain picks one when it is created and
+//! records it in its `chain.json`. Chains created before the choice existed use
+//! [`Pow::Sha256`].
+
+use std::fmt;
+use std::str::FromStr;
+
+use argon2::{Argon2, Params};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256 as Sha256Hasher};
+
+/// A hash function blocks are mined against.
+pub trait PowAlgorithm: Send + Sync {
+    /// Digest of a header preimage.
+    fn hash(&self, preimage: &[u8]) -> [u8; 32];
+}
+
+/// Single SHA-256, the original MChain hash.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256;
+
+/// SHA-256 applied twice, as in Bitcoin.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256d;
+
+/// BLAKE3 in its default 32-byte mode.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3;
+
+/// Argon2id with [`ARGON2_MEMORY_KIB`] of memory per hash, so each attempt costs memory
+/// bandwidth rather than just arithmetic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Argon2id;
+
+/// Memory used by each Argon2id hash, in KiB.
+pub const ARGON2_MEMORY_KIB: u32 = 4096;
+
+/// Argon2id passes over that memory.
+pub const ARGON2_PASSES: u32 = 1;
+
+/// Fixed salt: the preimage already makes every hash input unique.
+const ARGON2_SALT: &[u8] = b"mchain-pow";
+
+impl PowAlgorithm for Sha256 {
+    fn hash(&self, preimage: &[u8]) -> [u8; 32] {
+        Sha256Hasher::digest(preimage).into()
+    }
+}
+
+impl PowAlgorithm for Sha256d {
+    fn hash(&self, preimage: &[u8]) -> [u8; 32] {
+        Sha256Hasher::digest(Sha256Hasher::digest(preimage)).into()
+    }
+}
+
+impl PowAlgorithm for Blake3 {
+    fn hash(&self, preimage: &[u8]) -> [u8; 32] {
+        blake3::hash(preimage).into()
+    }
+}
+
+impl PowAlgorithm for Argon2id {
+    fn hash(&self, preimage: &[u8]) -> [u8; 32] {
+        let params = Params::new(ARGON2_MEMORY_KIB, ARGON2_PASSES, 1, Some(32)).expect("valid Argon2 parameters");
+        let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+        let mut digest = [0; 32];
+        argon2
+            .hash_password_into(preimage, ARGON2_SALT, &mut digest)
+            .expect("Argon2 accepts any preimage length");
+        digest
+    }
+}
+
+/// Which [`PowAlgorithm`] a chain uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Pow {
+    /// [`Sha256`].
+    #[default]
+    Sha256,
+    /// [`Sha256d`].
+    Sha256d,
+    /// [`Blake3`].
+    Blake3,
+    /// [`Argon2id`].
+    Argon2id,
+}
+
+impl FromStr for Pow {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(Pow::Sha256),
+            "sha256d" => Ok(Pow::Sha256d),
+            "blake3" => Ok(Pow::Blake3),
+            "argon2id" => Ok(Pow::Argon2id),
+            _ => Err(format!("unknown proof-of-work algorithm {:?} (expected sha256, sha256d, blake3 or argon2id)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Pow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Pow::Sha256 => "sha256",
+            Pow::Sha256d => "sha256d",
+            Pow::Blake3 => "blake3",
+            Pow::Argon2id => "argon2id",
+        })
+    }
+}
+
+impl Pow {
+    /// The hash function itself.
+    pub fn algorithm(self) -> &'static dyn PowAlgorithm {
+        match self {
+            Pow::Sha256 => &Sha256,
+            Pow::Sha256d => &Sha256d,
+            Pow::Blake3 => &Blake3,
+            Pow::Argon2id => &Argon2id,
+        }
+    }
+
+    /// Digest of `preimage` under this algorithm.
+    pub fn hash(self, preimage: &[u8]) -> [u8; 32] {
+        self.algorithm().hash(preimage)
+    }
+}
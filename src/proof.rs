@@ -0,0 +1,147 @@
+//! Self-contained proofs that a transaction is in a block.
+//!
+//! An [`InclusionProof`] carries one transaction, its Merkle audit path, and the headers
+//! from its block up to a checkpoint block. Checking it needs nothing else: the path
+//! leads from the transaction to the first header's Merkle root, and each header meets
+//! its own difficulty and is linked to by the next, so a verifier who trusts the
+//! checkpoint's hash can trust the transaction is in the chain behind it.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::{meets_difficulty, Block};
+use crate::error::{Error, Result};
+use crate::header::{hash_bytes, BlockHeader};
+use crate::hex;
+use crate::merkle;
+use crate::pow::Pow;
+use crate::transaction::Transaction;
+
+/// Proof that `transaction` is item `item` of the block `headers[0]` describes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// Hash function the chain is mined with.
+    pub pow: Pow,
+    /// Position of the transaction in its block.
+    pub item: usize,
+    /// Number of transactions in the block.
+    pub leaf_count: usize,
+    pub transaction: Transaction,
+    /// Sibling hashes from the transaction's leaf up to the Merkle root, hex encoded.
+    pub path: Vec<String>,
+    /// Headers from the transaction's block to the checkpoint, in chain order.
+    pub headers: Vec<BlockHeader>,
+}
+
+/// Why a proof failed to check out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofError {
+    /// The proof has no headers.
+    NoHeaders,
+    /// A path entry or header field is not a 64-digit hex hash.
+    Malformed { detail: String },
+    /// The audit path does not fit a tree of `leaf_count` leaves.
+    PathShape,
+    /// The audit path leads to a different root than the block header commits to.
+    RootMismatch { computed: String },
+    /// A header does not have the leading zero bits it records.
+    InsufficientWork { index: u64 },
+    /// A header does not link to the one before it.
+    BrokenLink { index: u64 },
+    /// The checkpoint's hash is not the one the verifier trusts.
+    UntrustedCheckpoint { hash: String },
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofError::NoHeaders => write!(f, "proof contains no block headers"),
+            ProofError::Malformed { detail } => write!(f, "malformed proof: {}", detail),
+            ProofError::PathShape => write!(f, "Merkle path does not fit the block's transaction count"),
+            ProofError::RootMismatch { computed } => {
+                write!(f, "Merkle path leads to {}, not the block's Merkle root", computed)
+            }
+            ProofError::InsufficientWork { index } => {
+                write!(f, "header of block {} does not meet its difficulty", index)
+            }
+            ProofError::BrokenLink { index } => {
+                write!(f, "header of block {} does not link to the block before it", index)
+            }
+            ProofError::UntrustedCheckpoint { hash } => {
+                write!(f, "checkpoint hash {} is not the trusted one", hash)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProofError {}
+
+impl InclusionProof {
+    /// Prove item `item` of block `block` in `blocks`, with headers up to `checkpoint`
+    /// (the last block if `None`).
+    pub fn build(blocks: &[Block], pow: Pow, block: u64, item: usize, checkpoint: Option<u64>) -> Result<Self> {
+        if block >= blocks.len() as u64 {
+            return Err(Error::Proof(format!("block {} is not in the chain", block)));
+        }
+        let checkpoint = checkpoint.unwrap_or(blocks.len() as u64 - 1);
+        if checkpoint < block {
+            return Err(Error::Proof(format!("checkpoint {} is before block {}", checkpoint, block)));
+        }
+        let chain = blocks
+            .get(block as usize..=checkpoint as usize)
+            .ok_or_else(|| Error::Proof(format!("checkpoint {} is not in the chain", checkpoint)))?;
+        let transactions = chain[0]
+            .body
+            .transactions()
+            .ok_or_else(|| Error::Proof(format!("block {} predates transactions", block)))?;
+        let transaction = transactions
+            .get(item)
+            .ok_or_else(|| Error::Proof(format!("block {} has only {} transaction(s)", block, transactions.len())))?;
+
+        let leaves: Vec<_> = transactions.iter().map(|tx| merkle::leaf_hash(&tx.encode())).collect();
+        Ok(InclusionProof {
+            pow,
+            item,
+            leaf_count: leaves.len(),
+            transaction: transaction.clone(),
+            path: merkle::proof(&leaves, item).iter().map(|h| hex::encode(h)).collect(),
+            headers: chain.iter().map(BlockHeader::of).collect::<Result<_>>()?,
+        })
+    }
+
+    /// Check the proof on its own, and against `trusted` if given, returning the
+    /// checkpoint header the transaction is anchored to and its hash.
+    pub fn verify(&self, trusted: Option<&str>) -> std::result::Result<(&BlockHeader, String), ProofError> {
+        let malformed = |e: Error| ProofError::Malformed { detail: e.to_string() };
+        let first = self.headers.first().ok_or(ProofError::NoHeaders)?;
+
+        let path = self.path.iter().map(|h| hash_bytes(h)).collect::<Result<Vec<_>>>().map_err(malformed)?;
+        let leaf = merkle::leaf_hash(&self.transaction.encode());
+        let root = merkle::root_from_proof(leaf, self.item, self.leaf_count, &path).ok_or(ProofError::PathShape)?;
+        if hash_bytes(&first.merkle_root).map_err(malformed)? != root {
+            return Err(ProofError::RootMismatch { computed: hex::encode(&root) });
+        }
+
+        let mut previous: Option<(u64, String)> = None;
+        for header in &self.headers {
+            let hash = header.hash(self.pow).map_err(malformed)?;
+            if let Some((index, hash)) = &previous
+                && (header.index != index + 1 || header.previous_hash != *hash)
+            {
+                return Err(ProofError::BrokenLink { index: header.index });
+            }
+            if !meets_difficulty(&hash_bytes(&hash).map_err(malformed)?, header.difficulty) {
+                return Err(ProofError::InsufficientWork { index: header.index });
+            }
+            previous = Some((header.index, hash));
+        }
+
+        let checkpoint = self.headers.last().ok_or(ProofError::NoHeaders)?;
+        let hash = previous.map(|(_, hash)| hash).unwrap_or_default();
+        if trusted.is_some_and(|trusted| !trusted.eq_ignore_ascii_case(&hash)) {
+            return Err(ProofError::UntrustedCheckpoint { hash });
+        }
+        Ok((checkpoint, hash))
+    }
+}
@@ -0,0 +1,79 @@
+//! Resumable `mine` runs.
+//!
+//! A run records what it set out to do in the chain's `session.json` before mining, and
+//! deletes it once done. A run that is interrupted, or killed outright, leaves the file
+//! behind for `mine --resume`.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::miner::Progress;
+use crate::store::{self, SESSION_FILE};
+
+/// An unfinished `mine` run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    /// Blocks the run set out to mine, not counting a genesis block.
+    pub target_blocks: u64,
+    /// Index of the first of those blocks.
+    pub first_index: u64,
+    /// Data template: each block gets `"<data> #<index>"`.
+    pub data: String,
+    /// Difficulty in bits the run was started with; retargeting chains override it.
+    pub difficulty: usize,
+    /// Index of the block being mined when the run was cancelled.
+    pub next_index: u64,
+    /// How far the search for `next_index` got.
+    #[serde(default)]
+    pub progress: Progress,
+    /// Address the block rewards are paid to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub miner_address: Option<String>,
+}
+
+impl Session {
+    /// A run mining `target_blocks` blocks starting at `first_index`.
+    pub fn new(target_blocks: u64, first_index: u64, data: impl Into<String>, difficulty: usize) -> Self {
+        Session {
+            target_blocks,
+            first_index,
+            data: data.into(),
+            difficulty,
+            next_index: first_index,
+            progress: Progress::default(),
+            miner_address: None,
+        }
+    }
+
+    /// Data for the block at `index`.
+    pub fn payload(&self, index: u64) -> String {
+        format!("{} #{}", self.data, index)
+    }
+
+    /// Blocks of the target still to mine once the chain's next index is `next_index`.
+    pub fn remaining(&self, next_index: u64) -> u64 {
+        (self.first_index + self.target_blocks).saturating_sub(next_index.max(self.first_index))
+    }
+
+    /// Where to start searching for the block at `index`: the saved progress if the run
+    /// was cancelled while mining that block, else the beginning.
+    pub fn progress_for(&self, index: u64) -> Progress {
+        if index == self.next_index { self.progress } else { Progress::default() }
+    }
+
+    /// The run left unfinished in `dir`, if any.
+    pub fn load(dir: &Path) -> Result<Option<Session>> {
+        store::read_side_file(dir, SESSION_FILE)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        store::write_side_file(dir, SESSION_FILE, self)
+    }
+
+    /// Mark the run in `dir` finished.
+    pub fn remove(dir: &Path) -> Result<()> {
+        store::remove_side_file(dir, SESSION_FILE)
+    }
+}
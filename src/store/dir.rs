@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+use super::{out_of_sequence, quarantine, BlockStore, LoadIssue, LoadReport, RESERVED_FILES};
+use crate::atomic::{self, write_atomic};
+use crate::block::Block;
+use crate::error::Result;
+
+/// One-JSON-file-per-block storage in a directory.
+#[derive(Debug, Clone)]
+pub struct DirStore {
+    dir: PathBuf,
+}
+
+impl DirStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DirStore { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of the file holding the block with `index`.
+    pub fn block_path(&self, index: u64) -> PathBuf {
+        self.dir.join(format!("block_{}.json", index))
+    }
+
+    /// Write `block` to `block_<index>.json`, creating the directory if needed.
+    ///
+    /// The write is atomic and durable: after a crash the file either holds the whole
+    /// block or does not exist. An interrupted write leaves only a `.tmp` file, which
+    /// loading ignores and the next save of that block overwrites.
+    pub fn save(&self, block: &Block) -> Result<()> {
+        if !self.dir.exists() {
+            fs::create_dir_all(&self.dir)?;
+            atomic::sync_dir(self.dir.parent().unwrap_or(Path::new("")))?;
+        }
+        let json = serde_json::to_string_pretty(block)?;
+        write_atomic(&self.block_path(block.index), json.as_bytes())?;
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<Block>> {
+        match File::open(path) {
+            Ok(file) => Ok(Some(serde_json::from_reader(BufReader::new(file))?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Every `.json` file in the directory that may hold a block.
+    fn block_files(&self) -> Result<Vec<PathBuf>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut files: Vec<_> = fs::read_dir(&self.dir)?
+            .filter_map(|f| f.ok())
+            .map(|f| f.path())
+            .filter(|p| p.is_file() && p.extension().map(|e| e == "json").unwrap_or(false))
+            .filter(|p| !p.file_name().is_some_and(|n| RESERVED_FILES.iter().any(|r| n == *r)))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// Highest index among correctly named block files, without parsing them.
+    fn highest_index(&self) -> Result<Option<u64>> {
+        Ok(self
+            .block_files()?
+            .iter()
+            .filter_map(|p| p.file_name()?.to_str()?.strip_prefix("block_")?.strip_suffix(".json")?.parse().ok())
+            .max())
+    }
+}
+
+impl BlockStore for DirStore {
+    fn append(&mut self, block: &Block) -> Result<()> {
+        let expected = self.len()?;
+        if block.index != expected {
+            return Err(out_of_sequence(expected, block));
+        }
+        self.save(block)
+    }
+
+    fn get(&self, index: u64) -> Result<Option<Block>> {
+        self.read(&self.block_path(index))
+    }
+
+    fn get_by_hash(&self, hash: &str) -> Result<Option<Block>> {
+        for block in self.iter()? {
+            let block = block?;
+            if block.hash == hash {
+                return Ok(Some(block));
+            }
+        }
+        Ok(None)
+    }
+
+    fn tip(&self) -> Result<Option<Block>> {
+        match self.highest_index()? {
+            Some(index) => self.get(index),
+            None => Ok(None),
+        }
+    }
+
+    fn len(&self) -> Result<u64> {
+        Ok(self.highest_index()?.map(|i| i + 1).unwrap_or(0))
+    }
+
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<Block>> + '_>> {
+        let len = self.len()?;
+        Ok(Box::new((0..len).map(move |index| {
+            self.get(index)?.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("block {} is missing", index)).into())
+        })))
+    }
+
+    /// Blocks are ordered by their embedded index, not by file name, and every file is
+    /// expected to be `block_<index>.json` for the block it holds.
+    fn scan(&self) -> Result<LoadReport> {
+        let mut issues = Vec::new();
+        let mut by_index: BTreeMap<u64, Vec<(PathBuf, Block)>> = BTreeMap::new();
+        for path in self.block_files()? {
+            let reader = BufReader::new(File::open(&path)?);
+            let block: Block = match serde_json::from_reader(reader) {
+                Ok(block) => block,
+                Err(error) => {
+                    issues.push(LoadIssue::Corrupt { path, error });
+                    continue;
+                }
+            };
+            if path != self.block_path(block.index) {
+                issues.push(LoadIssue::NameMismatch { path: path.clone(), index: block.index });
+            }
+            by_index.entry(block.index).or_default().push((path, block));
+        }
+
+        let mut blocks = Vec::with_capacity(by_index.len());
+        let mut expected = 0;
+        for (index, mut entries) in by_index {
+            issues.extend((expected..index).map(|index| LoadIssue::Gap { index }));
+            expected = index + 1;
+            if entries.len() > 1 {
+                let paths = entries.iter().map(|(p, _)| p.clone()).collect();
+                issues.push(LoadIssue::Duplicate { index, paths });
+            }
+            // Prefer the correctly named copy of a duplicated block.
+            let keep = entries.iter().position(|(p, _)| *p == self.block_path(index)).unwrap_or(0);
+            blocks.push(entries.swap_remove(keep).1);
+        }
+
+        Ok(LoadReport { blocks, issues })
+    }
+
+    /// Quarantines unparseable files, misnamed files, and blocks after the first gap.
+    fn repair(&mut self) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let report = self.scan()?;
+        let mut bad: Vec<PathBuf> = Vec::new();
+        let mut first_gap = None;
+        for issue in report.issues {
+            match issue {
+                LoadIssue::Corrupt { path, .. } | LoadIssue::NameMismatch { path, .. } => bad.push(path),
+                LoadIssue::Gap { index } if first_gap.is_none() => first_gap = Some(index),
+                _ => {}
+            }
+        }
+        if let Some(gap) = first_gap {
+            bad.extend(report.blocks.iter().filter(|b| b.index > gap).map(|b| self.block_path(b.index)));
+        }
+
+        let mut moved = Vec::with_capacity(bad.len());
+        for path in bad {
+            if path.exists() {
+                let target = quarantine(&self.dir, &path)?;
+                moved.push((path, target));
+            }
+        }
+        Ok(moved)
+    }
+
+    /// Deletes block files, leftover temp files and the quarantine directory, then the
+    /// directory itself if nothing else (such as other chains) is left in it.
+    fn reset(&mut self) -> Result<bool> {
+        if !self.dir.exists() {
+            return Ok(false);
+        }
+        let mut deleted = false;
+        for path in self.block_files()? {
+            fs::remove_file(&path)?;
+            deleted = true;
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|e| e == atomic::TEMP_EXTENSION) {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(deleted)
+    }
+
+    fn truncate(&mut self, len: u64) -> Result<Vec<Block>> {
+        let removed = (len..self.len()?)
+            .filter_map(|index| self.get(index).transpose())
+            .collect::<Result<Vec<_>>>()?;
+        for block in removed.iter().rev() {
+            fs::remove_file(self.block_path(block.index))?;
+        }
+        if !removed.is_empty() {
+            atomic::sync_dir(&self.dir)?;
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,261 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::{out_of_sequence, quarantine, unused_path, BlockStore, LoadIssue, LoadReport, QUARANTINE_DIR};
+use crate::atomic;
+use crate::block::Block;
+use crate::error::Result;
+
+/// Blocks per segment file.
+pub const SEGMENT_BLOCKS: u64 = 1000;
+
+/// Append-only log of blocks, one compact JSON record per line, split into segments of
+/// [`SEGMENT_BLOCKS`] blocks: `segment_000000.log` holds blocks 0–999, and so on.
+///
+/// Each append is fsynced. A crash mid-append leaves at most a torn last record, which
+/// `scan` reports and `repair` cuts off.
+#[derive(Debug, Clone)]
+pub struct LogStore {
+    dir: PathBuf,
+}
+
+/// One line of a segment: its 1-based line number and parse result.
+type Record = (usize, std::result::Result<Block, serde_json::Error>);
+
+impl LogStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        LogStore { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn segment_path(&self, segment: u64) -> PathBuf {
+        self.dir.join(format!("segment_{:06}.log", segment))
+    }
+
+    /// Numbers of the segment files present, ascending.
+    fn segments(&self) -> Result<Vec<u64>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut segments: Vec<u64> = fs::read_dir(&self.dir)?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().to_str()?.strip_prefix("segment_")?.strip_suffix(".log")?.parse().ok())
+            .collect();
+        segments.sort();
+        Ok(segments)
+    }
+
+    fn read_segment(&self, segment: u64) -> Result<String> {
+        let mut contents = String::new();
+        File::open(self.segment_path(segment))?.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+
+    fn records(contents: &str) -> impl Iterator<Item = Record> + '_ {
+        contents
+            .split_terminator('\n')
+            .enumerate()
+            .map(|(i, line)| (i + 1, serde_json::from_str(line)))
+    }
+
+    /// Byte offset where each line of `contents` starts.
+    fn line_offsets(contents: &str) -> Vec<usize> {
+        std::iter::once(0)
+            .chain(contents.match_indices('\n').map(|(i, _)| i + 1))
+            .filter(|&i| i < contents.len())
+            .collect()
+    }
+}
+
+impl BlockStore for LogStore {
+    fn append(&mut self, block: &Block) -> Result<()> {
+        let expected = self.len()?;
+        if block.index != expected {
+            return Err(out_of_sequence(expected, block));
+        }
+        fs::create_dir_all(&self.dir)?;
+        let path = self.segment_path(block.index / SEGMENT_BLOCKS);
+        let created = !path.exists();
+        let mut file = OpenOptions::new().read(true).append(true).create(true).open(&path)?;
+
+        let mut record = serde_json::to_vec(block)?;
+        record.push(b'\n');
+        // A crash between writing a record and its newline leaves a complete but
+        // unterminated record; terminate it before appending after it.
+        if file.seek(SeekFrom::End(0))? > 0 {
+            let mut last = [0; 1];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                record.insert(0, b'\n');
+            }
+        }
+        file.write_all(&record)?;
+        file.sync_data()?;
+        if created {
+            atomic::sync_dir(&self.dir)?;
+        }
+        Ok(())
+    }
+
+    fn get(&self, index: u64) -> Result<Option<Block>> {
+        let segment = index / SEGMENT_BLOCKS;
+        if !self.segment_path(segment).exists() {
+            return Ok(None);
+        }
+        let contents = self.read_segment(segment)?;
+        match Self::records(&contents).nth((index % SEGMENT_BLOCKS) as usize) {
+            Some((_, block)) => Ok(Some(block?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_by_hash(&self, hash: &str) -> Result<Option<Block>> {
+        for block in self.iter()? {
+            let block = block?;
+            if block.hash == hash {
+                return Ok(Some(block));
+            }
+        }
+        Ok(None)
+    }
+
+    fn tip(&self) -> Result<Option<Block>> {
+        match self.len()? {
+            0 => Ok(None),
+            len => self.get(len - 1),
+        }
+    }
+
+    fn len(&self) -> Result<u64> {
+        let Some(&last) = self.segments()?.last() else {
+            return Ok(0);
+        };
+        let records = self.read_segment(last)?.split_terminator('\n').count() as u64;
+        Ok(last * SEGMENT_BLOCKS + records)
+    }
+
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<Block>> + '_>> {
+        let segments = self.segments()?;
+        Ok(Box::new(segments.into_iter().flat_map(move |segment| {
+            let records: Vec<Result<Block>> = match self.read_segment(segment) {
+                Ok(contents) => Self::records(&contents).map(|(_, r)| r.map_err(Into::into)).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            records
+        })))
+    }
+
+    fn scan(&self) -> Result<LoadReport> {
+        let mut report = LoadReport::default();
+        let mut next_segment = 0;
+        for segment in self.segments()? {
+            if segment != next_segment {
+                report.issues.push(LoadIssue::Gap { index: next_segment * SEGMENT_BLOCKS });
+            }
+            next_segment = segment + 1;
+
+            let path = self.segment_path(segment);
+            let contents = self.read_segment(segment)?;
+            for (line, record) in Self::records(&contents) {
+                let expected = segment * SEGMENT_BLOCKS + line as u64 - 1;
+                match record {
+                    Ok(block) if block.index == expected => report.blocks.push(block),
+                    Ok(block) => report.issues.push(LoadIssue::OutOfOrder {
+                        path: path.clone(),
+                        line,
+                        expected,
+                        found: block.index,
+                    }),
+                    Err(error) => report.issues.push(LoadIssue::CorruptRecord { path: path.clone(), line, error }),
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Cuts the log at the first bad record: the rest of that segment is saved as
+    /// `corrupt/segment_<n>.log.tail` and every later segment is quarantined whole.
+    fn repair(&mut self) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let mut moved = Vec::new();
+        let mut cut = false;
+        for (position, segment) in self.segments()?.into_iter().enumerate() {
+            let path = self.segment_path(segment);
+            if cut || segment != position as u64 {
+                cut = true;
+                moved.push((path.clone(), quarantine(&self.dir, &path)?));
+                continue;
+            }
+
+            let contents = self.read_segment(segment)?;
+            let offsets = Self::line_offsets(&contents);
+            let bad = Self::records(&contents).position(|(line, record)| {
+                !matches!(record, Ok(block) if block.index == segment * SEGMENT_BLOCKS + line as u64 - 1)
+            });
+            if let Some(bad) = bad {
+                cut = true;
+                let quarantine_dir = self.dir.join(QUARANTINE_DIR);
+                fs::create_dir_all(&quarantine_dir)?;
+                let name = format!("{}.tail", path.file_name().unwrap_or_default().to_string_lossy());
+                let target = unused_path(&quarantine_dir, &name);
+                fs::write(&target, &contents.as_bytes()[offsets[bad]..])?;
+                let file = OpenOptions::new().write(true).open(&path)?;
+                file.set_len(offsets[bad] as u64)?;
+                file.sync_all()?;
+                moved.push((path, target));
+            }
+        }
+        Ok(moved)
+    }
+
+    fn reset(&mut self) -> Result<bool> {
+        if !self.dir.exists() {
+            return Ok(false);
+        }
+        let mut deleted = false;
+        for segment in self.segments()? {
+            fs::remove_file(self.segment_path(segment))?;
+            deleted = true;
+        }
+        Ok(deleted)
+    }
+
+    /// Deletes later segments whole, then cuts the segment holding block `len` short.
+    fn truncate(&mut self, len: u64) -> Result<Vec<Block>> {
+        let keep = len / SEGMENT_BLOCKS;
+        let mut removed = Vec::new();
+        for segment in self.segments()?.into_iter().filter(|&s| s >= keep) {
+            let contents = self.read_segment(segment)?;
+            let skip = if segment == keep { (len % SEGMENT_BLOCKS) as usize } else { 0 };
+            for (_, record) in Self::records(&contents).skip(skip) {
+                removed.push(record?);
+            }
+        }
+        for segment in self.segments()?.into_iter().rev().filter(|&s| s > keep) {
+            fs::remove_file(self.segment_path(segment))?;
+        }
+        let path = self.segment_path(keep);
+        if path.exists() {
+            let lines = (len % SEGMENT_BLOCKS) as usize;
+            if lines == 0 {
+                fs::remove_file(&path)?;
+            } else {
+                let contents = self.read_segment(keep)?;
+                let offsets = Self::line_offsets(&contents);
+                if let Some(&cut) = offsets.get(lines) {
+                    let file = OpenOptions::new().write(true).open(&path)?;
+                    file.set_len(cut as u64)?;
+                    file.sync_all()?;
+                }
+            }
+        }
+        if !removed.is_empty() {
+            atomic::sync_dir(&self.dir)?;
+        }
+        Ok(removed)
+    }
+}
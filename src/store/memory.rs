@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::{out_of_sequence, BlockStore, LoadReport};
+use crate::block::Block;
+use crate::error::Result;
+
+/// Blocks held in memory only; everything is lost when the store is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    blocks: Vec<Block>,
+    by_hash: HashMap<String, u64>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+}
+
+impl BlockStore for MemoryStore {
+    fn append(&mut self, block: &Block) -> Result<()> {
+        let expected = self.blocks.len() as u64;
+        if block.index != expected {
+            return Err(out_of_sequence(expected, block));
+        }
+        self.by_hash.insert(block.hash.clone(), block.index);
+        self.blocks.push(block.clone());
+        Ok(())
+    }
+
+    fn get(&self, index: u64) -> Result<Option<Block>> {
+        Ok(self.blocks.get(index as usize).cloned())
+    }
+
+    fn get_by_hash(&self, hash: &str) -> Result<Option<Block>> {
+        Ok(self.by_hash.get(hash).map(|&i| self.blocks[i as usize].clone()))
+    }
+
+    fn tip(&self) -> Result<Option<Block>> {
+        Ok(self.blocks.last().cloned())
+    }
+
+    fn len(&self) -> Result<u64> {
+        Ok(self.blocks.len() as u64)
+    }
+
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<Block>> + '_>> {
+        Ok(Box::new(self.blocks.iter().cloned().map(Ok)))
+    }
+
+    fn scan(&self) -> Result<LoadReport> {
+        Ok(LoadReport { blocks: self.blocks.clone(), issues: Vec::new() })
+    }
+
+    fn repair(&mut self) -> Result<Vec<(PathBuf, PathBuf)>> {
+        Ok(Vec::new())
+    }
+
+    fn reset(&mut self) -> Result<bool> {
+        let deleted = !self.blocks.is_empty();
+        self.blocks.clear();
+        self.by_hash.clear();
+        Ok(deleted)
+    }
+
+    fn truncate(&mut self, len: u64) -> Result<Vec<Block>> {
+        let removed = self.blocks.split_off((len as usize).min(self.blocks.len()));
+        for block in &removed {
+            self.by_hash.remove(&block.hash);
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,468 @@
+//! Block persistence.
+//!
+//! [`BlockStore`] is the interface the CLI and library use; each chain picks one backend,
+//! recorded in the chain's `chain.json`:
+//!
+//! - [`DirStore`]: one pretty-printed JSON file per block (`block_<index>.json`).
+//! - [`LogStore`]: append-only, newline-delimited JSON split into fixed-size segments.
+//! - [`MemoryStore`]: nothing on disk; for tests and throwaway runs.
+//! - `SqliteStore`: a local SQLite database indexed by height, hash and timestamp.
+//!   Only available with the `sqlite` cargo feature.
+
+mod dir;
+mod log;
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::atomic::write_atomic;
+use crate::block::Block;
+use crate::consensus::{Retarget, Reward};
+use crate::error::{Error, Result};
+use crate::mempool::Mempool;
+use crate::pow::Pow;
+use crate::session::Session;
+use crate::utxo::UtxoSet;
+
+pub use dir::DirStore;
+pub use log::{LogStore, SEGMENT_BLOCKS};
+pub use memory::MemoryStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+/// Directory used when no other location is given.
+pub const DEFAULT_DIR: &str = "mchain_data";
+
+/// Subdirectory that `repair` moves bad files into.
+pub const QUARANTINE_DIR: &str = "corrupt";
+
+/// Subdirectory of the data directory holding named chains.
+pub const CHAINS_DIR: &str = "chains";
+
+/// Per-chain settings file, kept next to the blocks.
+pub const CHAIN_FILE: &str = "chain.json";
+
+/// Unfinished mining run, kept next to the blocks. See [`Session`](crate::session::Session).
+pub const SESSION_FILE: &str = "session.json";
+
+/// Unspent outputs as of some block, kept next to the blocks. See
+/// [`UtxoSet`](crate::utxo::UtxoSet).
+pub const UTXO_FILE: &str = "utxo.json";
+
+/// Transactions waiting to be mined, kept next to the blocks. See
+/// [`Mempool`](crate::mempool::Mempool).
+pub const MEMPOOL_FILE: &str = "mempool.json";
+
+/// Database file of a `sqlite` chain, inside the chain directory.
+pub const DB_FILE: &str = "blocks.sqlite";
+
+/// JSON files in a chain directory that are not blocks.
+pub const RESERVED_FILES: &[&str] = &[CHAIN_FILE, SESSION_FILE, UTXO_FILE, MEMPOOL_FILE];
+
+/// Persistent, append-only storage for one chain.
+pub trait BlockStore {
+    /// Add `block` at the end. Its index must be the current length.
+    fn append(&mut self, block: &Block) -> Result<()>;
+
+    /// The block at `index`, if stored.
+    fn get(&self, index: u64) -> Result<Option<Block>>;
+
+    /// The block whose hash is `hash`, if stored.
+    fn get_by_hash(&self, hash: &str) -> Result<Option<Block>>;
+
+    /// The last block, if any.
+    fn tip(&self) -> Result<Option<Block>>;
+
+    /// Number of stored blocks.
+    fn len(&self) -> Result<u64>;
+
+    fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Every block in index order.
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<Block>> + '_>>;
+
+    /// Read every block, collecting problems instead of failing on them.
+    /// Only I/O errors are returned as `Err`.
+    fn scan(&self) -> Result<LoadReport>;
+
+    /// Every block in index order, or [`Error::Load`] listing every problem found.
+    fn load(&self) -> Result<Vec<Block>> {
+        self.scan()?.into_result()
+    }
+
+    /// Move whatever stops the store from loading into `corrupt/`, leaving a clean prefix
+    /// of the chain. Returns each moved file and its new location.
+    fn repair(&mut self) -> Result<Vec<(PathBuf, PathBuf)>>;
+
+    /// Delete every stored block, leaving anything `repair` quarantined in `corrupt/`.
+    /// Returns `false` if there was nothing to delete.
+    fn reset(&mut self) -> Result<bool>;
+
+    /// Delete every block from index `len` on, newest first, so that an interrupted
+    /// truncation still leaves a prefix of the chain. Returns the deleted blocks in index
+    /// order.
+    fn truncate(&mut self, len: u64) -> Result<Vec<Block>>;
+}
+
+/// A problem with the stored block files.
+#[derive(Debug)]
+pub enum LoadIssue {
+    /// The file is not a valid block.
+    Corrupt { path: PathBuf, error: serde_json::Error },
+    /// A record in a log segment is not a valid block.
+    CorruptRecord { path: PathBuf, line: usize, error: serde_json::Error },
+    /// No block with this index, though later ones exist.
+    Gap { index: u64 },
+    /// Several files hold a block with the same index.
+    Duplicate { index: u64, paths: Vec<PathBuf> },
+    /// The file name is not `block_<index>.json` for the block it holds.
+    NameMismatch { path: PathBuf, index: u64 },
+    /// A log record holds a different block than its position implies.
+    OutOfOrder { path: PathBuf, line: usize, expected: u64, found: u64 },
+    /// A database row is not a valid block.
+    CorruptRow { path: PathBuf, height: u64, error: serde_json::Error },
+    /// A database row holds a different block than its height.
+    RowMismatch { height: u64, index: u64 },
+}
+
+/// Every problem found while loading the store.
+#[derive(Debug)]
+pub struct LoadError {
+    pub issues: Vec<LoadIssue>,
+}
+
+/// Result of scanning the store without giving up on bad files.
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    /// Every block that parsed, ordered by index, one per index.
+    pub blocks: Vec<Block>,
+    pub issues: Vec<LoadIssue>,
+}
+
+impl fmt::Display for LoadIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadIssue::Corrupt { path, error } => write!(f, "{} is corrupt: {}", path.display(), error),
+            LoadIssue::CorruptRecord { path, line, error } => {
+                write!(f, "{} line {} is corrupt: {}", path.display(), line, error)
+            }
+            LoadIssue::Gap { index } => write!(f, "block {} is missing", index),
+            LoadIssue::Duplicate { index, paths } => {
+                write!(f, "block {} is stored {} times:", index, paths.len())?;
+                for path in paths {
+                    write!(f, " {}", path.display())?;
+                }
+                Ok(())
+            }
+            LoadIssue::NameMismatch { path, index } => {
+                write!(f, "{} holds block {}", path.display(), index)
+            }
+            LoadIssue::OutOfOrder { path, line, expected, found } => {
+                write!(f, "{} line {} holds block {}, expected {}", path.display(), line, found, expected)
+            }
+            LoadIssue::CorruptRow { path, height, error } => {
+                write!(f, "{} row {} is corrupt: {}", path.display(), height, error)
+            }
+            LoadIssue::RowMismatch { height, index } => write!(f, "row {} holds block {}", height, index),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block store has {} problem(s):", self.issues.len())?;
+        for issue in &self.issues {
+            write!(f, "\n  - {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl LoadReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The blocks, or every issue found if there were any.
+    pub fn into_result(self) -> Result<Vec<Block>> {
+        if self.is_clean() {
+            Ok(self.blocks)
+        } else {
+            Err(Error::Load(LoadError { issues: self.issues }))
+        }
+    }
+}
+
+/// Which [`BlockStore`] implementation a chain uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// [`DirStore`].
+    #[default]
+    Json,
+    /// [`LogStore`].
+    Log,
+    /// [`MemoryStore`].
+    Memory,
+    /// `SqliteStore`, if built with the `sqlite` feature.
+    Sqlite,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Backend::Json),
+            "log" => Ok(Backend::Log),
+            "memory" => Ok(Backend::Memory),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err(format!("unknown store backend {:?} (expected json, log, memory or sqlite)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Backend::Json => "json",
+            Backend::Log => "log",
+            Backend::Memory => "memory",
+            Backend::Sqlite => "sqlite",
+        })
+    }
+}
+
+impl Backend {
+    /// Open a store of this kind in `dir`.
+    pub fn open(self, dir: impl Into<PathBuf>) -> Result<Box<dyn BlockStore>> {
+        match self {
+            Backend::Json => Ok(Box::new(DirStore::new(dir))),
+            Backend::Log => Ok(Box::new(LogStore::new(dir))),
+            Backend::Memory => Ok(Box::new(MemoryStore::new())),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite => Ok(Box::new(SqliteStore::new(dir.into())?)),
+            #[cfg(not(feature = "sqlite"))]
+            Backend::Sqlite => Err(Error::FeatureDisabled("sqlite")),
+        }
+    }
+}
+
+/// Settings fixed when a chain is created, stored in its `chain.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChainConfig {
+    pub backend: Backend,
+    /// Difficulty retargeting rule, if the chain was created with one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retarget: Option<Retarget>,
+    /// Lowest difficulty in bits a block may record, for chains without retargeting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_difficulty: Option<usize>,
+    /// Proof-of-work hash function.
+    pub pow: Pow,
+    /// Block reward schedule, if the chain was created with one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reward: Option<Reward>,
+}
+
+impl ChainConfig {
+    /// Read `dir/chain.json`. `None` for chains created before the file existed, and for
+    /// chains that don't exist yet.
+    pub fn load(dir: &Path) -> Result<Option<ChainConfig>> {
+        read_side_file(dir, CHAIN_FILE)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        write_side_file(dir, CHAIN_FILE, self)
+    }
+
+    /// Delete `dir/chain.json`, if present.
+    pub fn remove(dir: &Path) -> Result<()> {
+        remove_side_file(dir, CHAIN_FILE)
+    }
+}
+
+/// Parse the JSON file `name` that sits beside the blocks in `dir`, or `None` if there
+/// is no such file.
+pub fn read_side_file<T: DeserializeOwned>(dir: &Path, name: &str) -> Result<Option<T>> {
+    match fs::read_to_string(dir.join(name)) {
+        Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Replace `dir/name` with `value` as pretty-printed JSON, atomically, creating `dir` if
+/// needed.
+pub fn write_side_file<T: Serialize>(dir: &Path, name: &str, value: &T) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    write_atomic(&dir.join(name), serde_json::to_string_pretty(value)?.as_bytes())?;
+    Ok(())
+}
+
+/// Delete `dir/name`; a file that is already gone is not an error.
+pub fn remove_side_file(dir: &Path, name: &str) -> Result<()> {
+    match fs::remove_file(dir.join(name)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Open the store for the chain in `dir`, with the backend [`resolve_backend`] picks.
+pub fn open(dir: &Path, backend: Option<Backend>) -> Result<Box<dyn BlockStore>> {
+    resolve_backend(dir, backend)?.open(dir)
+}
+
+/// The backend of the chain in `dir`: the one recorded in its `chain.json`, else the one
+/// whose files are already there (chains created before backends were selectable have
+/// no `chain.json`), else `requested`, else [`Backend::Json`]. Asking for a different
+/// backend than the existing chain's is an error.
+pub fn resolve_backend(dir: &Path, requested: Option<Backend>) -> Result<Backend> {
+    let existing = match ChainConfig::load(dir)? {
+        Some(config) => Some(config.backend),
+        None => backend_on_disk(dir)?,
+    };
+    match (existing, requested) {
+        (Some(recorded), Some(requested)) if recorded != requested => Err(Error::BackendMismatch { recorded, requested }),
+        (Some(backend), _) | (None, Some(backend)) => Ok(backend),
+        (None, None) => Ok(Backend::default()),
+    }
+}
+
+/// The backend whose block files are in `dir`, if any.
+fn backend_on_disk(dir: &Path) -> Result<Option<Backend>> {
+    if !DirStore::new(dir).is_empty()? {
+        Ok(Some(Backend::Json))
+    } else if !LogStore::new(dir).is_empty()? {
+        Ok(Some(Backend::Log))
+    } else if dir.join(DB_FILE).exists() {
+        Ok(Some(Backend::Sqlite))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Copy every block of the chain in `dir` into a `to` store in the same directory,
+/// record `to` in `chain.json`, then delete the old copy. The source must load cleanly
+/// and the target must be empty. Anything already quarantined in `corrupt/` stays there.
+/// Returns the number of blocks moved.
+pub fn migrate(dir: &Path, to: Backend) -> Result<u64> {
+    let mut config = ChainConfig::load(dir)?.unwrap_or_default();
+    if config.backend == to {
+        return Err(Error::Migration(format!("chain already uses the {} backend", to)));
+    }
+    if to == Backend::Memory {
+        return Err(Error::Migration("cannot migrate to the memory backend".to_string()));
+    }
+    let mut source = config.backend.open(dir)?;
+    let mut target = to.open(dir)?;
+    if !target.is_empty()? {
+        return Err(Error::Migration(format!("a {} store already exists in {}", to, dir.display())));
+    }
+
+    let blocks = source.load()?;
+    for block in &blocks {
+        target.append(block)?;
+    }
+    let copied = target.tip()?.map(|b| b.hash);
+    if target.len()? != blocks.len() as u64 || copied != blocks.last().map(|b| b.hash.clone()) {
+        return Err(Error::Migration("copied chain does not match the original".to_string()));
+    }
+
+    config.backend = to;
+    config.save(dir)?;
+    source.reset()?;
+    Ok(blocks.len() as u64)
+}
+
+/// Delete every block of the chain in `dir` along with its `chain.json`, any mining
+/// session, its unspent outputs, its pending transactions and its quarantined files,
+/// then the directory itself
+/// if nothing else (such as other chains) is left in it.
+pub fn reset_chain(dir: &Path, store: &mut dyn BlockStore) -> Result<bool> {
+    let mut deleted = store.reset()?;
+    match fs::remove_dir_all(dir.join(QUARANTINE_DIR)) {
+        Ok(()) => deleted = true,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    ChainConfig::remove(dir)?;
+    Session::remove(dir)?;
+    UtxoSet::remove(dir)?;
+    Mempool::remove(dir)?;
+    // Fails harmlessly when other chains still live inside this directory.
+    let _ = fs::remove_dir(dir);
+    Ok(deleted)
+}
+
+/// Directory for `chain` under `data_dir`. The unnamed chain lives directly in
+/// `data_dir`; named chains live in `data_dir/chains/<name>`.
+pub fn chain_dir(data_dir: impl AsRef<Path>, chain: Option<&str>) -> Result<PathBuf> {
+    let data_dir = data_dir.as_ref();
+    match chain {
+        None => Ok(data_dir.to_path_buf()),
+        Some(name) if is_valid_chain_name(name) => Ok(data_dir.join(CHAINS_DIR).join(name)),
+        Some(name) => Err(Error::InvalidChainName(name.to_string())),
+    }
+}
+
+/// Names of the chains stored under `data_dir/chains`, sorted.
+pub fn chain_names(data_dir: impl AsRef<Path>) -> Result<Vec<String>> {
+    let dir = data_dir.as_ref().join(CHAINS_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|name| is_valid_chain_name(name))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Chain names become directory names, so keep them to `[A-Za-z0-9_-]`.
+pub fn is_valid_chain_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+/// Move `path` into `dir/corrupt/`, never overwriting an earlier quarantined file.
+fn quarantine(dir: &Path, path: &Path) -> Result<PathBuf> {
+    let quarantine = dir.join(QUARANTINE_DIR);
+    fs::create_dir_all(&quarantine)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let target = unused_path(&quarantine, &name);
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// `dir/name`, or `dir/name.1`, `dir/name.2`, ... if that is taken.
+fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let mut target = dir.join(name);
+    let mut n = 1;
+    while target.exists() {
+        target = dir.join(format!("{}.{}", name, n));
+        n += 1;
+    }
+    target
+}
+
+/// Error for appending a block that doesn't extend the store.
+fn out_of_sequence(expected: u64, block: &Block) -> Error {
+    Error::OutOfSequence { expected, found: block.index }
+}
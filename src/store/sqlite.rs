@@ -0,0 +1,185 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+
+use super::{out_of_sequence, unused_path, BlockStore, LoadIssue, LoadReport, DB_FILE, QUARANTINE_DIR};
+use crate::block::Block;
+use crate::error::Result;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS blocks (
+        height    INTEGER PRIMARY KEY,
+        hash      TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        body      TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS blocks_hash ON blocks (hash);
+    CREATE INDEX IF NOT EXISTS blocks_timestamp ON blocks (timestamp);
+";
+
+/// Blocks in a local SQLite database, one row per block keyed by height, with the full
+/// block as JSON in `body` and indexes on hash and timestamp. Each append is its own
+/// transaction.
+#[derive(Debug)]
+pub struct SqliteStore {
+    path: PathBuf,
+    /// Opened on first use, so reading a chain that doesn't exist creates nothing.
+    conn: Option<Connection>,
+}
+
+impl SqliteStore {
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self> {
+        let path = dir.as_ref().join(DB_FILE);
+        let conn = if path.exists() { Some(Self::connect(&path)?) } else { None };
+        Ok(SqliteStore { path, conn })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn connect(path: &Path) -> Result<Connection> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "FULL")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(conn)
+    }
+
+    fn connection(&mut self) -> Result<&mut Connection> {
+        if self.conn.is_none() {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            self.conn = Some(Self::connect(&self.path)?);
+        }
+        Ok(self.conn.as_mut().unwrap())
+    }
+
+    fn query_one(&self, sql: &str, params: impl rusqlite::Params) -> Result<Option<Block>> {
+        let Some(conn) = &self.conn else {
+            return Ok(None);
+        };
+        let body: Option<String> = conn.query_row(sql, params, |row| row.get(0)).optional()?;
+        Ok(body.map(|b| serde_json::from_str(&b)).transpose()?)
+    }
+
+    /// Every row from height `from` up as `(height, body)`, by height.
+    fn rows(&self, from: u64) -> Result<Vec<(u64, String)>> {
+        let Some(conn) = &self.conn else {
+            return Ok(Vec::new());
+        };
+        let mut stmt = conn.prepare("SELECT height, body FROM blocks WHERE height >= ?1 ORDER BY height")?;
+        let rows = stmt.query_map([from], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+impl BlockStore for SqliteStore {
+    fn append(&mut self, block: &Block) -> Result<()> {
+        let body = serde_json::to_string(block)?;
+        let conn = self.connection()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let len: u64 = tx.query_row("SELECT COALESCE(MAX(height) + 1, 0) FROM blocks", [], |row| row.get(0))?;
+        if block.index != len {
+            return Err(out_of_sequence(len, block));
+        }
+        tx.execute(
+            "INSERT INTO blocks (height, hash, timestamp, body) VALUES (?1, ?2, ?3, ?4)",
+            params![block.index, block.hash, block.timestamp, body],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get(&self, index: u64) -> Result<Option<Block>> {
+        self.query_one("SELECT body FROM blocks WHERE height = ?1", [index])
+    }
+
+    fn get_by_hash(&self, hash: &str) -> Result<Option<Block>> {
+        self.query_one("SELECT body FROM blocks WHERE hash = ?1", [hash])
+    }
+
+    fn tip(&self) -> Result<Option<Block>> {
+        self.query_one("SELECT body FROM blocks ORDER BY height DESC LIMIT 1", [])
+    }
+
+    fn len(&self) -> Result<u64> {
+        let Some(conn) = &self.conn else {
+            return Ok(0);
+        };
+        Ok(conn.query_row("SELECT COALESCE(MAX(height) + 1, 0) FROM blocks", [], |row| row.get(0))?)
+    }
+
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<Block>> + '_>> {
+        let rows = self.rows(0)?;
+        Ok(Box::new(rows.into_iter().map(|(_, body)| Ok(serde_json::from_str(&body)?))))
+    }
+
+    fn scan(&self) -> Result<LoadReport> {
+        let mut report = LoadReport::default();
+        let mut expected = 0;
+        for (height, body) in self.rows(0)? {
+            report.issues.extend((expected..height).map(|index| LoadIssue::Gap { index }));
+            expected = height + 1;
+            match serde_json::from_str::<Block>(&body) {
+                Ok(block) if block.index == height => report.blocks.push(block),
+                Ok(block) => report.issues.push(LoadIssue::RowMismatch { height, index: block.index }),
+                Err(error) => report.issues.push(LoadIssue::CorruptRow { path: self.path.clone(), height, error }),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Deletes every row from the first bad one on, saving their raw bodies one per line
+    /// in `corrupt/blocks.sqlite.rows`.
+    fn repair(&mut self) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let rows = self.rows(0)?;
+        let bad = rows.iter().enumerate().position(|(position, (height, body))| {
+            *height != position as u64
+                || !matches!(serde_json::from_str::<Block>(body), Ok(block) if block.index == *height)
+        });
+        let Some(bad) = bad else {
+            return Ok(Vec::new());
+        };
+
+        let dir = self.path.parent().unwrap_or(Path::new("")).join(QUARANTINE_DIR);
+        fs::create_dir_all(&dir)?;
+        let target = unused_path(&dir, &format!("{}.rows", DB_FILE));
+        let dump: String = rows[bad..].iter().map(|(_, body)| format!("{}\n", body)).collect();
+        fs::write(&target, dump)?;
+
+        let from = rows[bad].0;
+        self.connection()?.execute("DELETE FROM blocks WHERE height >= ?1", [from])?;
+        Ok(vec![(self.path.clone(), target)])
+    }
+
+    fn reset(&mut self) -> Result<bool> {
+        self.conn = None;
+        let mut deleted = false;
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = self.path.clone().into_os_string();
+            path.push(suffix);
+            match fs::remove_file(&path) {
+                Ok(()) => deleted = true,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(deleted)
+    }
+
+    fn truncate(&mut self, len: u64) -> Result<Vec<Block>> {
+        let removed = self
+            .rows(len)?
+            .into_iter()
+            .map(|(_, body)| Ok(serde_json::from_str(&body)?))
+            .collect::<Result<Vec<_>>>()?;
+        if !removed.is_empty() {
+            self.connection()?.execute("DELETE FROM blocks WHERE height >= ?1", [len])?;
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,167 @@
+//! Records carried in a block body.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::hex;
+use crate::wallet::Keypair;
+
+/// Section tags in a transaction's canonical encoding.
+const TAG_DATA: u8 = 1;
+const TAG_COINBASE: u8 = 2;
+const TAG_OUTPUT: u8 = 3;
+const TAG_INPUT: u8 = 4;
+
+/// One record in a block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+    /// Free-form payload.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub data: String,
+    /// Height of the block whose reward this transaction pays out, for a coinbase.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coinbase: Option<u64>,
+    /// Amounts paid to addresses.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<Output>,
+    /// Earlier outputs this transaction spends.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<Input>,
+}
+
+/// An amount paid to an address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Output {
+    pub address: String,
+    /// Amount in base units; see [`COIN`](crate::amount::COIN).
+    pub amount: u64,
+}
+
+/// A reference to one output of an earlier transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct OutPoint {
+    #[serde(with = "hex::array")]
+    pub txid: [u8; 32],
+    /// Position of the output in that transaction.
+    pub vout: u32,
+}
+
+/// An output being spent, and the owner's authorisation to spend it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Input {
+    #[serde(flatten)]
+    pub outpoint: OutPoint,
+    /// Key whose address the spent output pays.
+    #[serde(with = "hex::array")]
+    pub public_key: [u8; 32],
+    /// That key's signature of [`Transaction::signing_bytes`].
+    #[serde(default, with = "hex::option_array", skip_serializing_if = "Option::is_none")]
+    pub signature: Option<[u8; 64]>,
+}
+
+impl Transaction {
+    /// A transaction carrying only `data`.
+    pub fn data(data: impl Into<String>) -> Self {
+        Transaction { data: data.into(), ..Transaction::default() }
+    }
+
+    /// The coinbase of the block at `height`, paying `outputs`.
+    pub fn coinbase(height: u64, outputs: Vec<Output>) -> Self {
+        Transaction { coinbase: Some(height), outputs, ..Transaction::default() }
+    }
+
+    /// An unsigned transaction spending `inputs`, each owned by `public_key`, to pay
+    /// `outputs`.
+    pub fn transfer(inputs: &[OutPoint], public_key: [u8; 32], outputs: Vec<Output>) -> Self {
+        let inputs = inputs.iter().map(|&outpoint| Input { outpoint, public_key, signature: None }).collect();
+        Transaction { inputs, outputs, ..Transaction::default() }
+    }
+
+    /// Sign every input owned by `keypair`.
+    pub fn sign(&mut self, keypair: &Keypair) {
+        let signature = keypair.sign(&self.signing_bytes());
+        for input in self.inputs.iter_mut().filter(|input| input.public_key == keypair.public_key()) {
+            input.signature = Some(signature);
+        }
+    }
+
+    /// The output spent by each input.
+    pub fn outpoints(&self) -> impl Iterator<Item = OutPoint> + '_ {
+        self.inputs.iter().map(|input| input.outpoint)
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.coinbase.is_some()
+    }
+
+    /// Sum of the output amounts, or `None` if it overflows.
+    pub fn output_total(&self) -> Option<u64> {
+        self.outputs.iter().try_fold(0u64, |total, out| total.checked_add(out.amount))
+    }
+
+    /// Canonical bytes: the transaction's hash and its Merkle leaf are computed over these.
+    ///
+    /// Each non-empty field is written as a one-byte tag, a little-endian `u64` length
+    /// and the field's bytes, in tag order. Empty fields are left out, so adding a field
+    /// never changes the encoding of transactions that don't use it. Each output is its
+    /// own section: the amount as a little-endian `u64`, then the address. So is each
+    /// input: the spent transaction's id, the output's position as a little-endian `u32`,
+    /// the public key, and the signature if there is one.
+    pub fn encode(&self) -> Vec<u8> {
+        self.encode_with(true)
+    }
+
+    /// What each input's signature signs: the canonical encoding without the signatures.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        self.encode_with(false)
+    }
+
+    fn encode_with(&self, signatures: bool) -> Vec<u8> {
+        let mut out = Vec::new();
+        if !self.data.is_empty() {
+            put_section(&mut out, TAG_DATA, self.data.as_bytes());
+        }
+        if let Some(height) = self.coinbase {
+            put_section(&mut out, TAG_COINBASE, &height.to_le_bytes());
+        }
+        for output in &self.outputs {
+            let mut bytes = output.amount.to_le_bytes().to_vec();
+            bytes.extend_from_slice(output.address.as_bytes());
+            put_section(&mut out, TAG_OUTPUT, &bytes);
+        }
+        for input in &self.inputs {
+            let mut bytes = input.outpoint.txid.to_vec();
+            bytes.extend_from_slice(&input.outpoint.vout.to_le_bytes());
+            bytes.extend_from_slice(&input.public_key);
+            if let Some(signature) = input.signature.filter(|_| signatures) {
+                bytes.extend_from_slice(&signature);
+            }
+            put_section(&mut out, TAG_INPUT, &bytes);
+        }
+        out
+    }
+
+    /// SHA-256 of the canonical encoding.
+    pub fn id(&self) -> [u8; 32] {
+        Sha256::digest(self.encode()).into()
+    }
+
+    /// [`Transaction::id`], hex encoded.
+    pub fn id_hex(&self) -> String {
+        hex::encode(&self.id())
+    }
+}
+
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", hex::encode(&self.txid), self.vout)
+    }
+}
+
+fn put_section(out: &mut Vec<u8>, tag: u8, bytes: &[u8]) {
+    out.push(tag);
+    out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
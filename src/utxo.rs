@@ -0,0 +1,223 @@
+//! Unspent transaction outputs.
+//!
+//! Coins live in transaction outputs. A transaction spends earlier outputs in full
+//! through its inputs and passes their value on through its own outputs; whatever it
+//! leaves unpaid is a fee the block's coinbase may claim. The unspent outputs are what
+//! each address can still spend.
+//!
+//! The set is kept in the chain's `utxo.json`, updated as blocks are appended, so that
+//! balances don't need every block replayed. It records the block it is current up to
+//! and is rebuilt from scratch when that block is no longer in the store.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::amount::format_coins;
+use crate::block::Block;
+use crate::error::{Error, Result};
+use crate::store::{self, BlockStore, UTXO_FILE};
+use crate::transaction::{Input, OutPoint, Output, Transaction};
+use crate::wallet::Keypair;
+
+/// Every output not yet spent, by the output it is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UtxoSet {
+    outputs: BTreeMap<OutPoint, Output>,
+    /// Number of blocks applied.
+    height: u64,
+    /// Hash of the last block applied.
+    tip: Option<String>,
+}
+
+/// `utxo.json`: the set and the block it is current up to.
+#[derive(Serialize, Deserialize)]
+struct UtxoFile {
+    height: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tip: Option<String>,
+    outputs: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    outpoint: OutPoint,
+    #[serde(flatten)]
+    output: Output,
+}
+
+impl UtxoSet {
+    pub fn new() -> Self {
+        UtxoSet::default()
+    }
+
+    /// The outputs `blocks` leave unspent.
+    pub fn from_blocks(blocks: &[Block]) -> Self {
+        let mut utxos = UtxoSet::new();
+        for block in blocks {
+            utxos.apply_block(block);
+        }
+        utxos
+    }
+
+    pub fn get(&self, outpoint: &OutPoint) -> Option<&Output> {
+        self.outputs.get(outpoint)
+    }
+
+    pub fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+
+    /// Number of blocks applied.
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    /// Hash of the last block applied.
+    pub fn tip(&self) -> Option<&str> {
+        self.tip.as_deref()
+    }
+
+    /// Apply every transaction in `block`, in order.
+    pub fn apply_block(&mut self, block: &Block) {
+        for tx in block.body.transactions().unwrap_or_default() {
+            self.apply(tx);
+        }
+        self.height = block.index + 1;
+        self.tip = Some(block.hash.clone());
+    }
+
+    /// Apply the blocks `store` has beyond the ones already applied. If the last block
+    /// applied is no longer in the store, as after a `reset` or `repair`, start over from
+    /// the first block. Returns the number of blocks applied.
+    pub fn catch_up(&mut self, store: &dyn BlockStore) -> Result<u64> {
+        if !self.is_current_prefix(store)? {
+            *self = UtxoSet::new();
+        }
+        let start = self.height;
+        let len = store.len()?;
+        for index in start..len {
+            let block = store.get(index)?.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("block {} is missing", index)))?;
+            self.apply_block(&block);
+        }
+        Ok(len.saturating_sub(start))
+    }
+
+    /// Whether the blocks applied are still the start of `store`.
+    fn is_current_prefix(&self, store: &dyn BlockStore) -> Result<bool> {
+        let Some(tip) = &self.tip else {
+            return Ok(self.height == 0);
+        };
+        let stored = match self.height.checked_sub(1) {
+            Some(index) => store.get(index)?,
+            None => None,
+        };
+        Ok(stored.is_some_and(|block| &block.hash == tip))
+    }
+
+    /// Remove the outputs `tx` spends and add the ones it creates. Inputs spending
+    /// outputs that are not in the set are ignored.
+    pub fn apply(&mut self, tx: &Transaction) {
+        for outpoint in tx.outpoints() {
+            self.outputs.remove(&outpoint);
+        }
+        let txid = tx.id();
+        for (vout, output) in tx.outputs.iter().enumerate() {
+            self.outputs.insert(OutPoint { txid, vout: vout as u32 }, output.clone());
+        }
+    }
+
+    /// Unspent outputs paying `address`.
+    pub fn owned_by<'a>(&'a self, address: &'a str) -> impl Iterator<Item = (&'a OutPoint, &'a Output)> {
+        self.outputs.iter().filter(move |(_, output)| output.address == address)
+    }
+
+    /// Total of the unspent outputs paying `address`.
+    pub fn balance(&self, address: &str) -> u64 {
+        self.owned_by(address).fold(0, |total, (_, output)| total.saturating_add(output.amount))
+    }
+
+    /// A transaction signed by `keypair` paying `amount` to `to` and leaving `fee` for the
+    /// miner, spending the largest of the keypair's outputs first and returning any
+    /// change to its own address.
+    pub fn pay(&self, keypair: &Keypair, to: &str, amount: u64, fee: u64) -> Result<Transaction> {
+        self.pay_from(std::slice::from_ref(keypair), to, amount, fee)
+    }
+
+    /// Like [`UtxoSet::pay`], spending the outputs of any of `keys` and returning the
+    /// change to the first.
+    pub fn pay_from(&self, keys: &[Keypair], to: &str, amount: u64, fee: u64) -> Result<Transaction> {
+        if amount == 0 {
+            return Err(Error::Wallet("the amount must be more than zero".to_string()));
+        }
+        if keys.is_empty() {
+            return Err(Error::Wallet("no keys to pay from".to_string()));
+        }
+        let needed = amount.checked_add(fee).ok_or_else(|| Error::Wallet("amount plus fee overflows".to_string()))?;
+        let addresses: Vec<String> = keys.iter().map(|key| key.address().to_string()).collect();
+        let mut owned: Vec<_> = self
+            .outputs
+            .iter()
+            .filter_map(|(outpoint, output)| Some((outpoint, output, addresses.iter().position(|a| *a == output.address)?)))
+            .collect();
+        owned.sort_by(|a, b| b.1.amount.cmp(&a.1.amount).then(a.0.cmp(b.0)));
+
+        let (mut inputs, mut total) = (Vec::new(), 0u64);
+        for (&outpoint, output, key) in owned {
+            if total >= needed {
+                break;
+            }
+            inputs.push(Input { outpoint, public_key: keys[key].public_key(), signature: None });
+            total = total.saturating_add(output.amount);
+        }
+        if total < needed {
+            let from = if keys.len() == 1 { addresses[0].clone() } else { format!("{} addresses", keys.len()) };
+            return Err(Error::Wallet(format!(
+                "{} has {} to spend but {} is needed",
+                from,
+                format_coins(total),
+                format_coins(needed)
+            )));
+        }
+
+        let mut outputs = vec![Output { address: to.to_string(), amount }];
+        if total > needed {
+            outputs.push(Output { address: addresses[0].clone(), amount: total - needed });
+        }
+        let mut tx = Transaction { inputs, outputs, ..Transaction::default() };
+        for key in keys {
+            tx.sign(key);
+        }
+        Ok(tx)
+    }
+
+    /// The set last saved for the chain in `dir`, which may lag its blocks; see
+    /// [`UtxoSet::catch_up`].
+    pub fn load(dir: &Path) -> Result<Option<UtxoSet>> {
+        let Some(file) = store::read_side_file::<UtxoFile>(dir, UTXO_FILE)? else {
+            return Ok(None);
+        };
+        let outputs = file.outputs.into_iter().map(|entry| (entry.outpoint, entry.output)).collect();
+        Ok(Some(UtxoSet { outputs, height: file.height, tip: file.tip }))
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let file = UtxoFile {
+            height: self.height,
+            tip: self.tip.clone(),
+            outputs: self.outputs.iter().map(|(&outpoint, output)| Entry { outpoint, output: output.clone() }).collect(),
+        };
+        store::write_side_file(dir, UTXO_FILE, &file)
+    }
+
+    pub fn remove(dir: &Path) -> Result<()> {
+        store::remove_side_file(dir, UTXO_FILE)
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::address::Address;
+use crate::amount::format_coins;
+use crate::block::{meets_difficulty, Block};
+use crate::chain::GENESIS_PREVIOUS_HASH;
+use crate::consensus::Consensus;
+use crate::header::MERKLE_VERSION;
+use crate::hex;
+use crate::transaction::{OutPoint, Transaction};
+use crate::utxo::UtxoSet;
+use crate::wallet::verify_signature;
+
+/// Why a block failed verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    /// The block's index does not follow its position in the chain.
+    IndexGap { expected: u64 },
+    /// The genesis block does not point at `"0"`.
+    BadGenesis { previous_hash: String },
+    /// `previous_hash` does not match the hash of the block before it.
+    BrokenLink { expected: String },
+    /// The stored hash is not the hash of the block's contents.
+    HashMismatch { computed: String },
+    /// The header's Merkle root is not the root of the block's transactions.
+    MerkleMismatch { computed: String },
+    /// The block's hash could not be recomputed at all.
+    Unhashable { detail: String },
+    /// The hash does not have as many leading zero bits as its difficulty requires.
+    InsufficientWork { bits: usize },
+    /// The recorded difficulty (in bits) is below the minimum the verifier accepts.
+    DifficultyTooLow { bits: usize, minimum: usize },
+    /// The recorded difficulty is not what the chain's retargeting rule requires.
+    WrongDifficulty { expected: usize, recorded: Option<usize> },
+    /// The timestamp is earlier than the previous block's.
+    TimestampRegression { previous: u64 },
+    /// The chain pays block rewards but the block does not start with a coinbase.
+    MissingCoinbase,
+    /// A coinbase other than the block's first transaction.
+    MisplacedCoinbase { item: usize },
+    /// The coinbase claims the reward of a different block.
+    CoinbaseHeight { recorded: u64 },
+    /// The coinbase pays out more than the subsidy plus fees, in base units.
+    ExcessiveCoinbase { claimed: u64, allowed: u64 },
+    /// The coinbase spends earlier outputs.
+    CoinbaseInputs,
+    /// A transaction pays out more than it spends, in base units.
+    Overspend { item: usize, spent: u64, available: u64 },
+    /// An input spends an output that doesn't exist or was already spent.
+    MissingInput { item: usize, input: usize, outpoint: OutPoint },
+    /// An input spends the same output as an earlier input of its transaction.
+    DuplicateInput { item: usize, input: usize },
+    /// An input's key is not the one the output it spends pays.
+    WrongKey { item: usize, input: usize },
+    /// An input is unsigned, or its signature doesn't verify.
+    BadSignature { item: usize, input: usize },
+}
+
+/// A single verification failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Position of the offending block in the chain.
+    pub position: usize,
+    /// Index recorded in the offending block.
+    pub index: u64,
+    pub reason: Reason,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::IndexGap { expected } => write!(f, "expected index {}", expected),
+            Reason::BadGenesis { previous_hash } => {
+                write!(f, "genesis previous_hash is {:?}, expected {:?}", previous_hash, GENESIS_PREVIOUS_HASH)
+            }
+            Reason::BrokenLink { expected } => write!(f, "previous_hash does not match {}", expected),
+            Reason::HashMismatch { computed } => write!(f, "stored hash does not match computed {}", computed),
+            Reason::MerkleMismatch { computed } => {
+                write!(f, "stored Merkle root does not match computed {}", computed)
+            }
+            Reason::Unhashable { detail } => write!(f, "cannot compute hash: {}", detail),
+            Reason::InsufficientWork { bits } => {
+                write!(f, "hash does not have {} leading zero bits", bits)
+            }
+            Reason::DifficultyTooLow { bits, minimum } => {
+                write!(f, "recorded difficulty of {} bits is below the minimum {}", bits, minimum)
+            }
+            Reason::WrongDifficulty { expected, recorded: Some(recorded) } => {
+                write!(f, "recorded difficulty of {} bits but retargeting requires {}", recorded, expected)
+            }
+            Reason::WrongDifficulty { expected, recorded: None } => {
+                write!(f, "no difficulty recorded but retargeting requires {} bits", expected)
+            }
+            Reason::TimestampRegression { previous } => {
+                write!(f, "timestamp is earlier than previous block's {}", previous)
+            }
+            Reason::MissingCoinbase => write!(f, "first transaction is not a coinbase"),
+            Reason::MisplacedCoinbase { item } => {
+                write!(f, "transaction {} is a coinbase; only the first may be", item)
+            }
+            Reason::CoinbaseHeight { recorded } => write!(f, "coinbase claims the reward of block {}", recorded),
+            Reason::ExcessiveCoinbase { claimed, allowed } => {
+                write!(f, "coinbase pays {} but at most {} is allowed", format_coins(*claimed), format_coins(*allowed))
+            }
+            Reason::CoinbaseInputs => write!(f, "coinbase spends earlier outputs"),
+            Reason::Overspend { item, spent, available } => {
+                write!(f, "transaction {} pays {} but only has {}", item, format_coins(*spent), format_coins(*available))
+            }
+            Reason::MissingInput { item, input, outpoint } => {
+                write!(f, "transaction {} input {} spends {}, which does not exist or was already spent", item, input, outpoint)
+            }
+            Reason::DuplicateInput { item, input } => {
+                write!(f, "transaction {} input {} spends the same output as an earlier input", item, input)
+            }
+            Reason::WrongKey { item, input } => {
+                write!(f, "transaction {} input {} is not signed for by the owner of the output it spends", item, input)
+            }
+            Reason::BadSignature { item, input } => {
+                write!(f, "transaction {} input {} has a missing or invalid signature", item, input)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block {} (position {}): {}", self.index, self.position, self.reason)
+    }
+}
+
+/// Check every block and return all violations found, in chain order.
+///
+/// Each block must carry its position as index, link to its predecessor (or `"0"` for
+/// genesis), hash to its stored `hash`, meet its recorded difficulty, and not go back in
+/// time. From version 4 on, the header's Merkle root must match the transactions.
+/// Difficulties are compared in leading zero bits. Recorded difficulties below
+/// `consensus.min_difficulty` are rejected; legacy blocks that record none must meet the
+/// minimum itself. On retargeting chains every block must record exactly the difficulty
+/// the rule computes from the blocks before it. On chains with block rewards every block
+/// must start with a coinbase for its own height, and no coinbase may pay out more than
+/// the block's subsidy plus fees. Every other transaction is checked by
+/// [`check_transaction`] against the outputs left unspent by the blocks before it.
+pub fn verify_blocks(blocks: &[Block], consensus: &Consensus) -> Vec<Violation> {
+    let min_difficulty = consensus.min_difficulty;
+    let mut utxos = UtxoSet::new();
+    let mut violations = Vec::new();
+    let mut report = |position: usize, block: &Block, reason: Reason| {
+        violations.push(Violation { position, index: block.index, reason });
+    };
+
+    for (position, block) in blocks.iter().enumerate() {
+        if block.index != position as u64 {
+            report(position, block, Reason::IndexGap { expected: position as u64 });
+        }
+
+        match position.checked_sub(1).map(|p| &blocks[p]) {
+            None if block.previous_hash != GENESIS_PREVIOUS_HASH => {
+                report(position, block, Reason::BadGenesis { previous_hash: block.previous_hash.clone() });
+            }
+            Some(prev) => {
+                if block.previous_hash != prev.hash {
+                    report(position, block, Reason::BrokenLink { expected: prev.hash.clone() });
+                }
+                if block.timestamp < prev.timestamp {
+                    report(position, block, Reason::TimestampRegression { previous: prev.timestamp });
+                }
+            }
+            None => {}
+        }
+
+        match block.compute_hash(consensus.pow) {
+            Ok(computed) if computed != block.hash => {
+                report(position, block, Reason::HashMismatch { computed });
+            }
+            Ok(_) => {}
+            Err(e) => report(position, block, Reason::Unhashable { detail: e.to_string() }),
+        }
+        if block.version >= MERKLE_VERSION
+            && let Some(computed) = block.body.merkle_root()
+            && block.merkle_root.as_ref() != Some(&computed)
+        {
+            report(position, block, Reason::MerkleMismatch { computed });
+        }
+        if let Some(transactions) = block.body.transactions() {
+            for reason in check_value(block.index, transactions, consensus, &mut utxos) {
+                report(position, block, reason);
+            }
+        }
+        if let Some(expected) = consensus.expected_difficulty(&blocks[..position])
+            && block.difficulty_bits() != Some(expected)
+        {
+            report(position, block, Reason::WrongDifficulty { expected, recorded: block.difficulty_bits() });
+        }
+        let bits = block.effective_difficulty(min_difficulty);
+        if bits < min_difficulty {
+            report(position, block, Reason::DifficultyTooLow { bits, minimum: min_difficulty });
+        }
+        let digest = hex::decode(&block.hash).unwrap_or_default();
+        if !meets_difficulty(&digest, bits) {
+            report(position, block, Reason::InsufficientWork { bits });
+        }
+    }
+
+    violations
+}
+
+/// Check that the transactions of the block at `height` create no more value than its
+/// reward allows, then apply them to `utxos`.
+fn check_value(height: u64, transactions: &[Transaction], consensus: &Consensus, utxos: &mut UtxoSet) -> Vec<Reason> {
+    let mut reasons = Vec::new();
+    let coinbase = transactions.first().filter(|tx| tx.is_coinbase());
+    match coinbase.and_then(|tx| tx.coinbase) {
+        None if consensus.reward.is_some() => reasons.push(Reason::MissingCoinbase),
+        Some(recorded) if recorded != height => reasons.push(Reason::CoinbaseHeight { recorded }),
+        _ => {}
+    }
+    if coinbase.is_some_and(|tx| !tx.inputs.is_empty()) {
+        reasons.push(Reason::CoinbaseInputs);
+    }
+
+    let mut fees = 0u64;
+    for (item, tx) in transactions.iter().enumerate().skip(coinbase.iter().len()) {
+        if tx.is_coinbase() {
+            reasons.push(Reason::MisplacedCoinbase { item });
+        }
+        match check_transaction(utxos, item, tx) {
+            Ok(fee) => fees = fees.saturating_add(fee),
+            Err(invalid) => reasons.extend(invalid),
+        }
+        utxos.apply(tx);
+    }
+
+    // The coinbase goes last so nothing in its own block can spend it.
+    if let Some(coinbase) = coinbase {
+        let subsidy = consensus.reward.as_ref().map_or(0, |r| r.subsidy(height));
+        let allowed = subsidy.saturating_add(fees);
+        let claimed = coinbase.output_total().unwrap_or(u64::MAX);
+        if claimed > allowed {
+            reasons.push(Reason::ExcessiveCoinbase { claimed, allowed });
+        }
+        utxos.apply(coinbase);
+    }
+    reasons
+}
+
+/// Check transaction `item` of a block against the outputs left unspent before it:
+/// each input must spend a different unspent output, with the key that output pays and
+/// a valid signature, and the outputs may not add up to more than the inputs. Returns
+/// the fee it leaves, or every reason it is invalid.
+pub fn check_transaction(utxos: &UtxoSet, item: usize, tx: &Transaction) -> Result<u64, Vec<Reason>> {
+    let mut reasons = Vec::new();
+    let message = tx.signing_bytes();
+    let mut seen = HashSet::new();
+    let (mut available, mut missing) = (0u64, false);
+    for (input, spend) in tx.inputs.iter().enumerate() {
+        if !seen.insert(spend.outpoint) {
+            reasons.push(Reason::DuplicateInput { item, input });
+            continue;
+        }
+        let Some(output) = utxos.get(&spend.outpoint) else {
+            reasons.push(Reason::MissingInput { item, input, outpoint: spend.outpoint });
+            missing = true;
+            continue;
+        };
+        if Address::from_public_key(&spend.public_key).to_string() != output.address {
+            reasons.push(Reason::WrongKey { item, input });
+        } else if !spend.signature.is_some_and(|sig| verify_signature(&spend.public_key, &message, &sig)) {
+            reasons.push(Reason::BadSignature { item, input });
+        }
+        available = available.saturating_add(output.amount);
+    }
+
+    // With an input missing, what it would have added is unknown.
+    let spent = tx.output_total().unwrap_or(u64::MAX);
+    if spent > available && !missing {
+        reasons.push(Reason::Overspend { item, spent, available });
+    }
+    if reasons.is_empty() { Ok(available - spent) } else { Err(reasons) }
+}
@@ -0,0 +1,360 @@
+//! Keypairs and the password-encrypted keystore.
+//!
+//! Each wallet is kept in `<data-dir>/wallets/<name>.json`, shared by every chain in the
+//! data directory. A wallet holds either one ed25519 keypair or, for a hierarchical
+//! deterministic wallet, the mnemonic its keys are derived from (see [`hd`](crate::hd)).
+//! The secret is encrypted with XChaCha20-Poly1305 under a key stretched from the
+//! wallet's password by Argon2id; addresses and public keys are stored in the clear so
+//! wallets can be listed and shown without the password.
+
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use argon2::{Argon2, Params};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::address::Address;
+use crate::atomic::write_atomic;
+use crate::error::{Error, Result};
+use crate::hd::{self, Mnemonic};
+use crate::hex;
+use crate::store::is_valid_chain_name;
+
+/// Subdirectory of the data directory holding the keystore.
+pub const WALLETS_DIR: &str = "wallets";
+
+/// Argon2id memory per password check, in KiB.
+const KDF_MEMORY_KIB: u32 = 19 * 1024;
+
+/// Argon2id passes over that memory.
+const KDF_PASSES: u32 = 2;
+
+/// Argon2id memory a wallet file may ask for, in KiB: Argon2's own minimum up to 1 GiB.
+const KDF_MEMORY_KIB_RANGE: RangeInclusive<u32> = 8..=1024 * 1024;
+
+/// Argon2id passes a wallet file may ask for.
+const KDF_PASSES_RANGE: RangeInclusive<u32> = 1..=64;
+
+const KDF: &str = "argon2id";
+const CIPHER: &str = "xchacha20-poly1305";
+
+/// Unused addresses in a row after which restoring a wallet stops looking for more.
+pub const GAP_LIMIT: u32 = 20;
+
+/// An ed25519 signing key.
+#[derive(Debug, Clone)]
+pub struct Keypair {
+    signing: SigningKey,
+}
+
+impl Keypair {
+    /// A new keypair from the operating system's random number generator.
+    pub fn generate() -> Result<Self> {
+        Ok(Keypair::from_secret(random()?))
+    }
+
+    pub fn from_secret(secret: [u8; 32]) -> Self {
+        Keypair { signing: SigningKey::from_bytes(&secret) }
+    }
+
+    pub fn secret(&self) -> [u8; 32] {
+        self.signing.to_bytes()
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.signing.verifying_key().to_bytes()
+    }
+
+    pub fn address(&self) -> Address {
+        Address::from_public_key(&self.public_key())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        self.signing.sign(message).to_bytes()
+    }
+}
+
+/// Whether `signature` is `public_key`'s signature of `message`.
+pub fn verify_signature(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    VerifyingKey::from_bytes(public_key)
+        .is_ok_and(|key| key.verify_strict(message, &Signature::from_bytes(signature)).is_ok())
+}
+
+/// One wallet file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub name: String,
+    pub address: String,
+    /// Hex encoded.
+    pub public_key: String,
+    /// Unix time the wallet was created.
+    pub created: u64,
+    pub crypto: SealedKey,
+    /// Keys derived so far, for a wallet sealing a mnemonic rather than a single key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hd: Option<HdKeys>,
+}
+
+/// The keys an HD wallet has derived from its mnemonic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HdKeys {
+    /// Account the addresses belong to; see [`hd::path`].
+    pub account: u32,
+    /// Address `i` is at index `i`. The first is the wallet's own address.
+    pub keys: Vec<DerivedKey>,
+}
+
+/// The public half of a derived key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivedKey {
+    pub address: String,
+    /// Hex encoded.
+    pub public_key: String,
+}
+
+impl DerivedKey {
+    fn new(keypair: &Keypair) -> Self {
+        DerivedKey { address: keypair.address().to_string(), public_key: hex::encode(&keypair.public_key()) }
+    }
+}
+
+/// A secret key encrypted under a password.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedKey {
+    /// Always `argon2id`.
+    pub kdf: String,
+    pub memory_kib: u32,
+    pub passes: u32,
+    /// Hex encoded.
+    pub salt: String,
+    /// Always `xchacha20-poly1305`.
+    pub cipher: String,
+    /// Hex encoded.
+    pub nonce: String,
+    /// Encrypted secret key and authentication tag, hex encoded.
+    pub ciphertext: String,
+}
+
+impl Keystore {
+    /// Encrypt `keypair` under `password` as the wallet `name`.
+    pub fn seal(name: &str, keypair: &Keypair, password: &str) -> Result<Keystore> {
+        Keystore::seal_secret(name, &keypair.secret(), keypair, password)
+    }
+
+    /// Encrypt `mnemonic` under `password` as the wallet `name`, with the first `count`
+    /// addresses of `account` derived (at least one).
+    pub fn seal_hd(name: &str, mnemonic: &Mnemonic, account: u32, count: u32, password: &str) -> Result<Keystore> {
+        let seed = mnemonic.seed();
+        let keys: Vec<_> = (0..count.max(1)).map(|index| hd::derive(&seed, account, index)).collect();
+        let mut keystore = Keystore::seal_secret(name, &mnemonic.entropy(), &keys[0], password)?;
+        keystore.hd = Some(HdKeys { account, keys: keys.iter().map(DerivedKey::new).collect() });
+        Ok(keystore)
+    }
+
+    /// Rebuild the wallet `name` from `mnemonic`, deriving addresses of `account` until
+    /// [`GAP_LIMIT`] in a row are unused, and keeping them up to the last one `used`.
+    pub fn restore(name: &str, mnemonic: &Mnemonic, account: u32, password: &str, used: impl Fn(&str) -> bool) -> Result<Keystore> {
+        let seed = mnemonic.seed();
+        let (mut index, mut count) = (0, 0);
+        while index < count + GAP_LIMIT {
+            if used(&hd::derive(&seed, account, index).address().to_string()) {
+                count = index + 1;
+            }
+            index += 1;
+        }
+        Keystore::seal_hd(name, mnemonic, account, count, password)
+    }
+
+    fn seal_secret(name: &str, secret: &[u8], keypair: &Keypair, password: &str) -> Result<Keystore> {
+        if !is_valid_name(name) {
+            return Err(Error::Wallet(format!("invalid wallet name {:?}: use letters, digits, '_' and '-'", name)));
+        }
+        let salt: [u8; 16] = random()?;
+        let nonce: [u8; 24] = random()?;
+        let cipher = cipher(password, &salt, KDF_MEMORY_KIB, KDF_PASSES)?;
+        let ciphertext = cipher
+            .encrypt(&XNonce::from(nonce), secret)
+            .map_err(|_| Error::Wallet("encryption failed".to_string()))?;
+        let created = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        Ok(Keystore {
+            name: name.to_string(),
+            address: keypair.address().to_string(),
+            public_key: hex::encode(&keypair.public_key()),
+            created,
+            crypto: SealedKey {
+                kdf: KDF.to_string(),
+                memory_kib: KDF_MEMORY_KIB,
+                passes: KDF_PASSES,
+                salt: hex::encode(&salt),
+                cipher: CIPHER.to_string(),
+                nonce: hex::encode(&nonce),
+                ciphertext: hex::encode(&ciphertext),
+            },
+            hd: None,
+        })
+    }
+
+    /// Every address of the wallet, its own first.
+    pub fn addresses(&self) -> Vec<&str> {
+        match &self.hd {
+            Some(hd) => hd.keys.iter().map(|key| key.address.as_str()).collect(),
+            None => vec![self.address.as_str()],
+        }
+    }
+
+    /// Decrypt the wallet's own keypair with `password`.
+    pub fn unlock(&self, password: &str) -> Result<Keypair> {
+        Ok(self.unlock_all(password)?.swap_remove(0))
+    }
+
+    /// Decrypt every keypair of the wallet with `password`, in the order of
+    /// [`Keystore::addresses`].
+    pub fn unlock_all(&self, password: &str) -> Result<Vec<Keypair>> {
+        let secret = self.decrypt(password)?;
+        let keys = match &self.hd {
+            Some(hd) => {
+                let seed = Mnemonic::from_entropy(&secret).ok_or_else(|| self.corrupt())?.seed();
+                (0..hd.keys.len() as u32).map(|index| hd::derive(&seed, hd.account, index)).collect()
+            }
+            None => vec![Keypair::from_secret(secret.try_into().map_err(|_| self.corrupt())?)],
+        };
+        let addresses = keys.iter().map(|key| key.address().to_string());
+        if keys.is_empty() || keys[0].address().to_string() != self.address || !addresses.eq(self.addresses()) {
+            return Err(self.corrupt());
+        }
+        Ok(keys)
+    }
+
+    /// The mnemonic of an HD wallet, decrypted with `password`; `None` for a single-key
+    /// wallet.
+    pub fn mnemonic(&self, password: &str) -> Result<Option<Mnemonic>> {
+        if self.hd.is_none() {
+            return Ok(None);
+        }
+        let entropy = self.decrypt(password)?;
+        Mnemonic::from_entropy(&entropy).map(Some).ok_or_else(|| self.corrupt())
+    }
+
+    /// Derive the HD wallet's next address, decrypting its mnemonic with `password`.
+    pub fn derive_next(&mut self, password: &str) -> Result<Keypair> {
+        let mnemonic = self.mnemonic(password)?.ok_or_else(|| Error::Wallet(format!("wallet {} has a single key", self.name)))?;
+        let hd = self.hd.as_mut().expect("only HD wallets have a mnemonic");
+        let keypair = hd::derive(&mnemonic.seed(), hd.account, hd.keys.len() as u32);
+        hd.keys.push(DerivedKey::new(&keypair));
+        Ok(keypair)
+    }
+
+    fn decrypt(&self, password: &str) -> Result<Vec<u8>> {
+        let sealed = &self.crypto;
+        if sealed.kdf != KDF || sealed.cipher != CIPHER {
+            return Err(Error::Wallet(format!("wallet {} uses an unsupported {} / {}", self.name, sealed.kdf, sealed.cipher)));
+        }
+        let salt = hex::decode(&sealed.salt).ok_or_else(|| self.corrupt())?;
+        let nonce: [u8; 24] = hex::decode(&sealed.nonce).and_then(|n| n.try_into().ok()).ok_or_else(|| self.corrupt())?;
+        let ciphertext = hex::decode(&sealed.ciphertext).ok_or_else(|| self.corrupt())?;
+        cipher(password, &salt, sealed.memory_kib, sealed.passes)?
+            .decrypt(&XNonce::from(nonce), ciphertext.as_slice())
+            .map_err(|_| Error::Wallet(format!("wrong password for wallet {}", self.name)))
+    }
+
+    fn corrupt(&self) -> Error {
+        Error::Wallet(format!("wallet {} is corrupt", self.name))
+    }
+
+    /// Read the wallet `name` from `data_dir`, if it exists.
+    pub fn load(data_dir: &Path, name: &str) -> Result<Option<Keystore>> {
+        if !is_valid_name(name) {
+            return Ok(None);
+        }
+        match fs::read_to_string(path(data_dir, name)) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the wallet into `data_dir`, refusing to replace an existing one.
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        let path = path(data_dir, &self.name);
+        if path.exists() {
+            return Err(Error::Wallet(format!("wallet {} already exists", self.name)));
+        }
+        fs::create_dir_all(data_dir.join(WALLETS_DIR))?;
+        write_atomic(&path, serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Write the wallet over its existing file in `data_dir`, as after deriving a key.
+    pub fn update(&self, data_dir: &Path) -> Result<()> {
+        let path = path(data_dir, &self.name);
+        if !path.exists() {
+            return Err(Error::Wallet(format!("no wallet named {}", self.name)));
+        }
+        write_atomic(&path, serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Every wallet in `data_dir`, sorted by name.
+    pub fn list(data_dir: &Path) -> Result<Vec<Keystore>> {
+        let dir = data_dir.join(WALLETS_DIR);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut wallets = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                wallets.push(serde_json::from_str::<Keystore>(&fs::read_to_string(&path)?)?);
+            }
+        }
+        wallets.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(wallets)
+    }
+}
+
+/// Wallet names become file names, so they follow the same rule as chain names.
+pub fn is_valid_name(name: &str) -> bool {
+    is_valid_chain_name(name)
+}
+
+fn path(data_dir: &Path, name: &str) -> PathBuf {
+    data_dir.join(WALLETS_DIR).join(format!("{}.json", name))
+}
+
+/// Cipher keyed by Argon2id over `password`. Parameters outside the sane ranges are
+/// refused, so a doctored wallet file can't make unlocking exhaust memory or time.
+fn cipher(password: &str, salt: &[u8], memory_kib: u32, passes: u32) -> Result<XChaCha20Poly1305> {
+    if !KDF_MEMORY_KIB_RANGE.contains(&memory_kib) {
+        return Err(Error::Wallet(format!(
+            "argon2id memory of {} KiB is outside {} to {} KiB",
+            memory_kib,
+            KDF_MEMORY_KIB_RANGE.start(),
+            KDF_MEMORY_KIB_RANGE.end()
+        )));
+    }
+    if !KDF_PASSES_RANGE.contains(&passes) {
+        return Err(Error::Wallet(format!(
+            "argon2id passes of {} is outside {} to {}",
+            passes,
+            KDF_PASSES_RANGE.start(),
+            KDF_PASSES_RANGE.end()
+        )));
+    }
+    let params = Params::new(memory_kib, passes, 1, Some(32)).map_err(|e| Error::Wallet(e.to_string()))?;
+    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    let mut key = [0u8; 32];
+    argon2.hash_password_into(password.as_bytes(), salt, &mut key).map_err(|e| Error::Wallet(e.to_string()))?;
+    XChaCha20Poly1305::new_from_slice(&key).map_err(|_| Error::Wallet("bad key length".to_string()))
+}
+
+pub(crate) fn random<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    getrandom::fill(&mut bytes).map_err(io::Error::other)?;
+    Ok(bytes)
+}
@@ -0,0 +1,86 @@
+//! The `mchain` binary run end to end against scratch data directories.
+
+mod common;
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use mchain::store::{self, ChainConfig};
+use mchain::{Backend, BlockStore, DirStore, Error, LogStore};
+
+use common::scratch_dir;
+
+/// Run `mchain --data-dir <dir> <args>`, from inside `dir` so no config file is picked up.
+fn mchain(dir: &Path, args: &[&str]) -> Output {
+    std::fs::create_dir_all(dir).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_mchain"))
+        .current_dir(dir)
+        .env("MCHAIN_PLATFORM_POLICY", "any")
+        .env_remove("MCHAIN_CONFIG")
+        .env_remove("MCHAIN_DATA_DIR")
+        .env_remove("MCHAIN_CHAIN")
+        .arg("--data-dir")
+        .arg(dir.join("data"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn succeeds(output: Output) -> String {
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    assert!(output.status.success(), "{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+    stdout
+}
+
+#[test]
+fn mining_after_a_repair_keeps_the_chain_on_its_backend() {
+    let dir = scratch_dir("cli-repair-mine");
+    let data = dir.join("data");
+    succeeds(mchain(&dir, &["--store", "log", "mine", "--bits", "4", "--blocks", "2"]));
+    let log = LogStore::new(&data);
+    std::fs::write(log.segment_path(0), "{\"index\": 0, \"trunc\n").unwrap();
+
+    succeeds(mchain(&dir, &["repair"]));
+    assert!(log.is_empty().unwrap());
+    succeeds(mchain(&dir, &["mine", "--bits", "4", "--blocks", "1"]));
+
+    assert_eq!(ChainConfig::load(&data).unwrap().unwrap().backend, Backend::Log);
+    assert_eq!(log.load().unwrap().len(), 2);
+    assert!(DirStore::new(&data).is_empty().unwrap());
+    assert!(succeeds(mchain(&dir, &["list"])).contains("Block 1 |"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_store_backend_conflicting_with_existing_blocks_is_refused() {
+    let dir = scratch_dir("cli-backend-conflict");
+    let data = dir.join("data");
+    succeeds(mchain(&dir, &["mine", "--bits", "4", "--blocks", "1"]));
+    // A chain from before chain.json existed.
+    std::fs::remove_file(data.join(store::CHAIN_FILE)).unwrap();
+
+    let refused = mchain(&dir, &["--store", "log", "mine", "--bits", "4", "--blocks", "1"]);
+    assert!(!refused.status.success());
+    assert!(LogStore::new(&data).is_empty().unwrap());
+    assert!(matches!(
+        store::resolve_backend(&data, Some(Backend::Log)),
+        Err(Error::BackendMismatch { recorded: Backend::Json, requested: Backend::Log })
+    ));
+
+    succeeds(mchain(&dir, &["mine", "--bits", "4", "--blocks", "1"]));
+    assert_eq!(DirStore::new(&data).len().unwrap(), 3);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn verify_holds_a_chain_to_the_difficulty_it_was_created_with() {
+    let dir = scratch_dir("cli-verify-floor");
+    succeeds(mchain(&dir, &["mine", "--bits", "8", "--blocks", "2"]));
+    assert_eq!(ChainConfig::load(&dir.join("data")).unwrap().unwrap().min_difficulty, Some(8));
+    assert!(succeeds(mchain(&dir, &["verify"])).contains("All 3 blocks are valid"));
+
+    let strict = mchain(&dir, &["verify", "--bits", "12"]);
+    assert!(!strict.status.success());
+    assert!(String::from_utf8_lossy(&strict.stdout).contains("below the minimum 12"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
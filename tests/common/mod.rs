@@ -0,0 +1,42 @@
+//! Fixtures shared by the integration tests.
+
+// Each test crate compiles its own copy and uses only some of it.
+#![allow(dead_code)]
+
+use std::env;
+use std::path::PathBuf;
+
+use mchain::{Block, Chain, Keypair, Miner, Reward, Transaction};
+
+/// An empty directory under the system temp dir, unique to `name` and this test run.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("mchain-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+pub fn alice() -> Keypair {
+    Keypair::from_secret([1; 32])
+}
+
+pub fn bob() -> Keypair {
+    Keypair::from_secret([2; 32])
+}
+
+pub fn address(keypair: &Keypair) -> String {
+    keypair.address().to_string()
+}
+
+/// Lowercase hex of `bytes`.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Mine a block of `txs` onto `chain` at 4 bits, with a coinbase paying `miner` the
+/// default subsidy plus `fees`.
+pub fn mine(chain: &mut Chain, miner: &Keypair, txs: Vec<Transaction>, fees: u64) -> Block {
+    let height = chain.next_index();
+    let mut block = vec![Reward::default().coinbase(height, fees, Some(&address(miner)))];
+    block.extend(txs);
+    chain.mine_next(&Miner::new(4).with_threads(1), block).unwrap().block
+}
@@ -0,0 +1,112 @@
+//! Difficulty retargeting, driven by block times like those in the sample `mchain_data`.
+
+use mchain::block::{leading_zero_bits, meets_difficulty};
+use mchain::{Block, Body, DifficultyUnit, Retarget};
+
+/// Version-2 blocks at `difficulty` bits whose timestamps advance by each of
+/// `durations_ms`, the way the miner stamps a block when it solves it: each block's time
+/// is the previous one's plus how long the block itself took to mine.
+fn blocks(difficulty: usize, durations_ms: &[u128]) -> Vec<Block> {
+    blocks_of_version(2, difficulty, durations_ms)
+}
+
+fn blocks_of_version(version: u32, difficulty: usize, durations_ms: &[u128]) -> Vec<Block> {
+    let mut timestamp = 1_749_431_131;
+    durations_ms
+        .iter()
+        .enumerate()
+        .map(|(index, &ms)| {
+            timestamp += (ms / 1000) as u64;
+            Block {
+                version,
+                index: index as u64,
+                timestamp,
+                body: Body::Legacy { data: String::new() },
+                nonce: 0,
+                extra_nonce: 0,
+                difficulty: Some(difficulty),
+                previous_hash: String::new(),
+                merkle_root: None,
+                hash: String::new(),
+                mining_duration_ms: ms,
+                platform: None,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn holds_difficulty_between_retargets() {
+    let rule = Retarget::new(20, 4, 60);
+    assert_eq!(rule.next_difficulty(&[]), 20);
+    assert_eq!(rule.next_difficulty(&blocks(20, &[4839, 3517, 245960])), 20);
+}
+
+#[test]
+fn fast_blocks_raise_difficulty() {
+    // The sample genesis and block 1 took 3-5 s against a 60 s target: about 14x too
+    // fast, clamped to 2 bits.
+    let rule = Retarget::new(20, 4, 60);
+    assert_eq!(rule.next_difficulty(&blocks(20, &[4839, 3517, 4839, 3517])), 22);
+}
+
+#[test]
+fn slightly_fast_blocks_raise_difficulty_one_bit() {
+    // 2 s per block against a 3 s target is 1.5x too fast, past the √2 midpoint.
+    let rule = Retarget::new(20, 4, 3);
+    assert_eq!(rule.next_difficulty(&blocks(20, &[2000, 2000, 2000, 2000])), 21);
+}
+
+#[test]
+fn slow_blocks_lower_difficulty() {
+    // Blocks 2 and 3 took 111-246 s against a 20 s target.
+    let rule = Retarget::new(24, 4, 20);
+    assert_eq!(rule.next_difficulty(&blocks(24, &[245960, 111898, 245960, 111898])), 22);
+}
+
+#[test]
+fn near_target_blocks_keep_difficulty() {
+    // The sample's mixed times average about 91 s per block.
+    let rule = Retarget::new(20, 4, 90);
+    assert_eq!(rule.next_difficulty(&blocks(20, &[4839, 3517, 245960, 111898])), 20);
+}
+
+#[test]
+fn adjustment_is_clamped() {
+    let mut rule = Retarget::new(12, 4, 100_000);
+    let fast = blocks(12, &[1000, 1000, 1000, 1000]);
+    assert_eq!(rule.next_difficulty(&fast), 14);
+    rule.max_step = 5;
+    assert_eq!(rule.next_difficulty(&fast), 17);
+}
+
+#[test]
+fn difficulty_never_drops_below_one_bit() {
+    let rule = Retarget::new(1, 4, 1);
+    assert_eq!(rule.next_difficulty(&blocks(1, &[245960, 245960, 245960, 245960])), 1);
+}
+
+#[test]
+fn hex_digit_chains_keep_retargeting_in_whole_digits() {
+    // A rule saved before bit targets: 5 hex digits, one digit per adjustment.
+    let mut rule = Retarget::new(5, 4, 60);
+    rule.unit = DifficultyUnit::HexDigits;
+    rule.max_step = 1;
+    assert_eq!(rule.next_difficulty(&[]), 20);
+    // Version-1 blocks record hex digits; the rule answers in bits.
+    assert_eq!(rule.next_difficulty(&blocks_of_version(1, 5, &[4839, 3517, 4839, 3517])), 24);
+    assert_eq!(rule.next_difficulty(&blocks_of_version(1, 5, &[4839, 3517, 245960, 111898])), 20);
+    // Once blocks record bits the steps stay 4 bits wide.
+    assert_eq!(rule.next_difficulty(&blocks(24, &[245960, 245960, 245960, 245960])), 20);
+}
+
+#[test]
+fn leading_zero_bits_of_raw_digests() {
+    assert_eq!(leading_zero_bits(&[0x00, 0x00, 0x1f, 0xff]), 19);
+    assert_eq!(leading_zero_bits(&[0x80]), 0);
+    assert_eq!(leading_zero_bits(&[0; 4]), 32);
+    assert!(meets_difficulty(&[0x00, 0x0f], 12));
+    assert!(!meets_difficulty(&[0x00, 0x1f], 12));
+    assert!(meets_difficulty(&[0x00], 8));
+    assert!(!meets_difficulty(&[0x00], 9));
+}
@@ -0,0 +1,77 @@
+//! Block hash preimages: the legacy string format and the binary layouts that replaced it.
+
+mod common;
+
+use std::path::Path;
+
+use mchain::header::{self, EXTRA_NONCE_OFFSET, NONCE_OFFSET, TIMESTAMP_OFFSET};
+use mchain::{calculate_hash, verify_blocks, Block, BlockStore, Body, Consensus, DirStore, Transaction};
+use sha2::{Digest, Sha256};
+
+use common::hex;
+
+fn block(version: u32, index: u64, timestamp: u64) -> Block {
+    Block {
+        version,
+        index,
+        timestamp,
+        body: Body::Legacy { data: "data".to_string() },
+        nonce: 0x0102_0304_0506_0708,
+        extra_nonce: 0x1112_1314_1516_1718,
+        difficulty: Some(2),
+        previous_hash: "0".to_string(),
+        merkle_root: None,
+        hash: String::new(),
+        mining_duration_ms: 0,
+        platform: None,
+    }
+}
+
+#[test]
+fn binary_preimages_tell_index_and_timestamp_apart() {
+    // "1" + "23" and "12" + "3" run together into the same legacy string.
+    let legacy = header::preimage(&block(0, 1, 23)).unwrap();
+    assert_eq!(legacy, header::preimage(&block(0, 12, 3)).unwrap());
+
+    for version in 1..=3 {
+        let (a, b) = (block(version, 1, 23), block(version, 12, 3));
+        assert_ne!(header::preimage(&a).unwrap(), header::preimage(&b).unwrap(), "version {}", version);
+        assert_ne!(calculate_hash(&a).unwrap(), calculate_hash(&b).unwrap(), "version {}", version);
+    }
+}
+
+#[test]
+fn legacy_blocks_still_hash_and_verify() {
+    let sample = DirStore::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("mchain_data")).load().unwrap();
+    assert!(sample.iter().all(|b| b.version == 0 && b.difficulty.is_none()));
+
+    let genesis = &sample[0];
+    let Body::Legacy { data } = &genesis.body else { unreachable!() };
+    let input = format!("{}{}{}{}{}", genesis.index, genesis.timestamp, data, genesis.nonce, genesis.previous_hash);
+    assert_eq!(hex(&Sha256::digest(input.as_bytes())), genesis.hash);
+    assert_eq!(calculate_hash(genesis).unwrap(), genesis.hash);
+
+    let consensus = Consensus { min_difficulty: 20, ..Consensus::default() };
+    assert_eq!(verify_blocks(&sample, &consensus), []);
+}
+
+#[test]
+fn offsets_point_at_the_bytes_preimage_writes() {
+    for version in [1, 2, 3] {
+        let block = block(version, 5, 0x2122_2324_2526_2728);
+        let preimage = header::preimage(&block).unwrap();
+        assert_eq!(preimage[TIMESTAMP_OFFSET..TIMESTAMP_OFFSET + 8], block.timestamp.to_le_bytes());
+        assert_eq!(preimage[NONCE_OFFSET..NONCE_OFFSET + 8], block.nonce.to_le_bytes());
+        if version >= 3 {
+            assert_eq!(preimage[EXTRA_NONCE_OFFSET..EXTRA_NONCE_OFFSET + 8], block.extra_nonce.to_le_bytes());
+        }
+    }
+
+    let body = Body::Transactions { transactions: vec![Transaction::data("data")] };
+    let block = Block { merkle_root: body.merkle_root(), body, ..block(4, 5, 0x2122_2324_2526_2728) };
+    let preimage = header::preimage(&block).unwrap();
+    assert_eq!(preimage.len(), 104);
+    assert_eq!(preimage[TIMESTAMP_OFFSET..TIMESTAMP_OFFSET + 8], block.timestamp.to_le_bytes());
+    assert_eq!(preimage[NONCE_OFFSET..NONCE_OFFSET + 8], block.nonce.to_le_bytes());
+    assert_eq!(preimage[EXTRA_NONCE_OFFSET..EXTRA_NONCE_OFFSET + 8], block.extra_nonce.to_le_bytes());
+}
@@ -0,0 +1,130 @@
+//! Pending transactions: admission, block templates, and what happens when blocks come
+//! and go.
+
+mod common;
+
+use mchain::amount::COIN;
+use mchain::verify::Reason;
+use mchain::{BlockStore, Chain, DirStore, LogStore, Mempool, MemoryStore, Output, Transaction, UtxoSet};
+
+use common::{address, alice, bob, mine, scratch_dir};
+
+/// A chain paying alice three block rewards.
+fn funded_chain() -> Chain {
+    let mut chain = Chain::new(Vec::new());
+    for _ in 0..3 {
+        mine(&mut chain, &alice(), Vec::new(), 0);
+    }
+    chain
+}
+
+#[test]
+fn pending_outputs_cannot_be_spent_twice() {
+    let utxos = UtxoSet::from_blocks(funded_chain().blocks());
+    let mut mempool = Mempool::new();
+    let first = utxos.pay(&alice(), &address(&bob()), 10 * COIN, COIN).unwrap();
+    assert_eq!(mempool.add(first.clone(), &utxos), Ok(COIN));
+
+    // Built without looking at the mempool, it spends the same output again.
+    let conflict = utxos.pay(&alice(), &address(&bob()), 20 * COIN, 0).unwrap();
+    let outpoint = conflict.inputs[0].outpoint;
+    assert_eq!(mempool.add(conflict, &utxos), Err(vec![Reason::MissingInput { item: 1, input: 0, outpoint }]));
+
+    // Built on top of it, it spends the change instead.
+    let chained = mempool.spendable(&utxos).pay(&alice(), &address(&bob()), 120 * COIN, 0).unwrap();
+    assert!(chained.outpoints().any(|outpoint| outpoint.txid == first.id()));
+    assert!(mempool.add(chained, &utxos).is_ok());
+    assert_eq!(mempool.len(), 2);
+}
+
+#[test]
+fn templates_take_the_highest_fees_that_fit() {
+    let utxos = UtxoSet::from_blocks(funded_chain().blocks());
+    let mut mempool = Mempool::new();
+    let mut spendable = utxos.clone();
+    let mut sizes = Vec::new();
+    for fee in [1, 3, 2] {
+        let tx = spendable.pay(&alice(), &address(&bob()), COIN, fee * COIN).unwrap();
+        spendable.apply(&tx);
+        sizes.push(tx.encode().len());
+        mempool.add(tx, &utxos).unwrap();
+    }
+    assert_eq!(mempool.fees(&utxos), [COIN, 3 * COIN, 2 * COIN]);
+
+    let pending = mempool.transactions();
+    let (all, total) = mempool.select(&utxos, usize::MAX);
+    assert_eq!(all, [pending[1].clone(), pending[2].clone(), pending[0].clone()]);
+    assert_eq!(total, 6 * COIN);
+
+    let (two, total) = mempool.select(&utxos, sizes[1] + sizes[2]);
+    assert_eq!(two, [pending[1].clone(), pending[2].clone()]);
+    assert_eq!(total, 5 * COIN);
+    assert_eq!(mempool.select(&utxos, sizes[1] - 1), (Vec::new(), 0));
+}
+
+#[test]
+fn a_child_waits_for_its_parent() {
+    let utxos = UtxoSet::from_blocks(funded_chain().blocks());
+    let mut mempool = Mempool::new();
+    // Spend all three rewards at a low fee, then the change at a high one.
+    let parent = utxos.pay(&alice(), &address(&bob()), 100 * COIN, COIN).unwrap();
+    mempool.add(parent.clone(), &utxos).unwrap();
+    let child = mempool.spendable(&utxos).pay(&alice(), &address(&bob()), 40 * COIN, 5 * COIN).unwrap();
+    mempool.add(child.clone(), &utxos).unwrap();
+
+    let (picked, fees) = mempool.select(&utxos, usize::MAX);
+    assert_eq!(picked, [parent.clone(), child]);
+    assert_eq!(fees, 6 * COIN);
+    assert!(mempool.select(&utxos, parent.encode().len() - 1).0.is_empty());
+}
+
+#[test]
+fn mined_transactions_leave_and_orphaned_ones_return() {
+    let dir = scratch_dir("mempool-orphan");
+    let stores: Vec<Box<dyn BlockStore>> =
+        vec![Box::new(MemoryStore::new()), Box::new(DirStore::new(dir.join("dir"))), Box::new(LogStore::new(dir.join("log")))];
+    for mut store in stores {
+        let mut chain = funded_chain();
+        for block in chain.blocks() {
+            store.append(block).unwrap();
+        }
+        let mut utxos = UtxoSet::from_blocks(chain.blocks());
+        let mut mempool = Mempool::new();
+        let paid = utxos.pay(&alice(), &address(&bob()), 10 * COIN, COIN).unwrap();
+        mempool.add(paid.clone(), &utxos).unwrap();
+        let later = mempool.spendable(&utxos).pay(&alice(), &address(&bob()), 5 * COIN, 0).unwrap();
+        mempool.add(later.clone(), &utxos).unwrap();
+
+        // A block including the first transaction...
+        let (picked, fees) = mempool.select(&utxos, paid.encode().len());
+        let block = mine(&mut chain, &alice(), picked, fees);
+        store.append(&block).unwrap();
+        utxos.apply_block(&block);
+        assert_eq!(mempool.remove_included(&block, &utxos), 1);
+        assert_eq!(mempool.transactions(), std::slice::from_ref(&later));
+
+        // ...and one spending the output the second one does.
+        let mut rival = Transaction::transfer(&[later.inputs[0].outpoint], alice().public_key(), vec![Output {
+            address: address(&bob()),
+            amount: 6 * COIN,
+        }]);
+        rival.sign(&alice());
+        let next = mine(&mut chain, &alice(), vec![rival.clone()], 0);
+        store.append(&next).unwrap();
+        utxos.apply_block(&next);
+        assert_eq!(mempool.remove_included(&next, &utxos), 1);
+        assert!(mempool.is_empty());
+
+        // Orphaning both puts their transfers back, in chain order.
+        let orphaned = store.truncate(3).unwrap();
+        assert_eq!(orphaned, [block, next]);
+        assert_eq!(store.len().unwrap(), 3);
+        let mut utxos = UtxoSet::new();
+        utxos.catch_up(store.as_ref()).unwrap();
+        assert_eq!(mempool.readmit(&orphaned, &utxos), 2);
+        assert_eq!(mempool.transactions(), [paid, rival]);
+        assert!(store.truncate(3).unwrap().is_empty());
+        assert_eq!(store.load().unwrap(), &chain.blocks()[..3]);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,55 @@
+//! Transactions, Merkle roots and the legacy string body.
+
+use mchain::merkle::{leaf_hash, node_hash, root, transactions_root};
+use mchain::verify::Reason;
+use mchain::{Block, Body, Chain, Consensus, Miner, Transaction};
+
+fn leaves(n: usize) -> Vec<[u8; 32]> {
+    (0..n).map(|i| leaf_hash(format!("tx {}", i).as_bytes())).collect()
+}
+
+#[test]
+fn uneven_trees_split_at_a_power_of_two() {
+    let l = leaves(5);
+    assert_eq!(root(&l[..1]), l[0]);
+    assert_eq!(root(&l[..3]), node_hash(&node_hash(&l[0], &l[1]), &l[2]));
+    let four = node_hash(&node_hash(&l[0], &l[1]), &node_hash(&l[2], &l[3]));
+    assert_eq!(root(&l), node_hash(&four, &l[4]));
+}
+
+#[test]
+fn repeating_the_last_transaction_changes_the_root() {
+    let a = Transaction::data("a");
+    let b = Transaction::data("b");
+    let c = Transaction::data("c");
+    let three = transactions_root(&[a.clone(), b.clone(), c.clone()]);
+    assert_ne!(three, transactions_root(&[a, b, c.clone(), c]));
+}
+
+#[test]
+fn legacy_blocks_load_as_a_string_body() {
+    let json = r#"{"index":1,"timestamp":1749431136,"data":"Resumed Mining #1","nonce":584868,
+        "previous_hash":"00000fc9d4a5e0fcf4102bf732bc79ddadef504ce5614fced6be74a6a4177ecf",
+        "hash":"000008538a024c1579d06e4e74fe59a106edfb919e2738302bbc1adbe7933bdc","mining_duration_ms":3517}"#;
+    let block: Block = serde_json::from_str(json).unwrap();
+    assert_eq!(block.body, Body::Legacy { data: "Resumed Mining #1".to_string() });
+    assert_eq!(block.compute_hash(Default::default()).unwrap(), block.hash);
+    assert!(!serde_json::to_string(&block).unwrap().contains("transactions"));
+}
+
+#[test]
+fn header_commits_to_every_transaction() {
+    let miner = Miner::new(4).with_threads(1);
+    let mut chain = Chain::new(Vec::new());
+    let txs = vec![Transaction::data("first"), Transaction::data("second"), Transaction::data("third")];
+    chain.mine_next(&miner, txs).unwrap();
+    let consensus = Consensus { min_difficulty: 4, ..Consensus::default() };
+    assert!(chain.verify(&consensus).is_empty());
+
+    let mut blocks = chain.blocks().to_vec();
+    let Body::Transactions { transactions } = &mut blocks[0].body else { unreachable!() };
+    transactions[1].data = "forged".to_string();
+    let violations = Chain::new(blocks).verify(&consensus);
+    assert_eq!(violations.len(), 1);
+    assert!(matches!(violations[0].reason, Reason::MerkleMismatch { .. }));
+}
@@ -0,0 +1,83 @@
+//! Miner behaviour beyond a single nonce sweep.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use mchain::{calculate_hash, Chain, Consensus, Error, Miner, Transaction};
+
+#[test]
+fn exhausted_nonces_roll_the_extra_nonce() {
+    // Eight nonces per extra nonce make a 10-bit target all but certain to need several.
+    let miner = Miner::new(10).with_threads(2).with_max_nonce(7);
+    let mut chain = Chain::new(Vec::new());
+    let mined = chain.mine_next(&miner, vec![Transaction::data("genesis")]).unwrap();
+    assert!(mined.block.nonce <= 7);
+    assert!(mined.block.extra_nonce > 0);
+
+    chain.mine_next(&miner, vec![Transaction::data("next")]).unwrap();
+    let consensus = Consensus { min_difficulty: 10, ..Consensus::default() };
+    assert!(chain.verify(&consensus).is_empty());
+}
+
+#[test]
+fn nonces_stay_within_max_nonce_when_threads_do_not_divide_it() {
+    // Three workers over nonces 0..=7: the last stride would hand one of them nonce 8.
+    let miner = Miner::new(10).with_threads(3).with_max_nonce(7);
+    let mut chain = Chain::new(Vec::new());
+    for i in 0..30 {
+        let mined = chain.mine_next(&miner, vec![Transaction::data(format!("block {}", i))]).unwrap();
+        assert!(mined.block.nonce <= 7, "block {} used nonce {}", i, mined.block.nonce);
+    }
+}
+
+/// Seconds handed out by [`ticking_clock`], which moves on by one on every read.
+static CLOCK: AtomicU64 = AtomicU64::new(1_700_000_000);
+
+fn ticking_clock() -> u64 {
+    CLOCK.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+#[test]
+fn timestamp_is_refreshed_while_mining() {
+    // A 16-bit target takes tens of thousands of hashes, so the worker reads the clock
+    // many times before it finds one.
+    let miner = Miner::new(16).with_threads(1).with_clock(ticking_clock);
+    let started = CLOCK.load(Ordering::Relaxed) + 1;
+    let mined = miner.mine(0, vec![Transaction::data("genesis")], "0").unwrap();
+    let block = &mined.block;
+
+    assert!(mined.hashes > 1000, "only {} hashes", mined.hashes);
+    assert!(block.timestamp > started + 1, "timestamp {} was never refreshed from {}", block.timestamp, started);
+    // The worker stops reading the clock once it has a solution.
+    assert_eq!(block.timestamp, CLOCK.load(Ordering::Relaxed));
+    assert_eq!(calculate_hash(block).unwrap(), block.hash);
+    assert!(block.hash.starts_with("0000"));
+}
+
+#[test]
+fn cancelled_search_resumes_where_it_stopped() {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let miner = Miner::new(40).with_threads(2).with_cancel(cancel.clone());
+    let stopper = {
+        let cancel = cancel.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            cancel.store(true, Ordering::Relaxed);
+        })
+    };
+    let Err(Error::Cancelled { progress, hashes }) = miner.mine(0, vec![Transaction::data("genesis")], "0") else {
+        panic!("a 40-bit block should not be found in 200 ms");
+    };
+    stopper.join().unwrap();
+    // Progress is the furthest point both workers got past, so no more than was hashed.
+    assert!(hashes > 0);
+    assert!(progress.nonce <= hashes);
+
+    // Every nonce below the saved progress was tried, so the resumed search starts there.
+    let miner = Miner::new(4).with_threads(3);
+    let mined = miner.resume(0, vec![Transaction::data("genesis")], "0", progress).unwrap();
+    assert!(mined.block.nonce >= progress.nonce);
+    assert_eq!(mined.block.extra_nonce, 0);
+}
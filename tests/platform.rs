@@ -0,0 +1,67 @@
+//! Host detection from `/proc/cpuinfo` and the policies deciding where MChain runs.
+
+use mchain::platform::{parse_cpuinfo, Verdict};
+use mchain::{Platform, PlatformPolicy};
+
+const X86: &str = "processor\t: 0
+vendor_id\t: GenuineIntel
+model name\t: Intel(R) Xeon(R) CPU @ 2.20GHz
+flags\t\t: fpu vme de
+";
+
+const ASAHI: &str = "processor\t: 0
+BogoMIPS\t: 48.00
+Features\t: fp asimd evtstrm aes
+CPU implementer\t: 0x61
+CPU architecture: 8
+";
+
+const GRAVITON: &str = "processor\t: 0
+CPU implementer\t: 0x41
+CPU part\t: 0xd0c
+";
+
+fn platform(arch: &'static str, cpuinfo: &str) -> Platform {
+    Platform { os: "linux", arch, cpu: parse_cpuinfo(cpuinfo) }
+}
+
+#[test]
+fn cpuinfo_yields_a_brand() {
+    assert_eq!(parse_cpuinfo(X86).as_deref(), Some("Intel(R) Xeon(R) CPU @ 2.20GHz"));
+    assert_eq!(parse_cpuinfo(ASAHI).as_deref(), Some("Apple Silicon"));
+    assert_eq!(parse_cpuinfo(GRAVITON).as_deref(), Some("ARM implementer 0x41"));
+    assert_eq!(parse_cpuinfo("processor\t: 0\n"), None);
+    assert_eq!(parse_cpuinfo(""), None);
+
+    assert!(platform("aarch64", ASAHI).is_apple_silicon());
+    assert!(!platform("aarch64", GRAVITON).is_apple_silicon());
+    assert!(!platform("x86_64", X86).is_apple_silicon());
+    assert!(!platform("aarch64", "").is_apple_silicon());
+}
+
+#[test]
+fn policies_decide_who_may_run() {
+    let apple = platform("aarch64", ASAHI);
+    let other = platform("x86_64", X86);
+    let cases = [
+        (PlatformPolicy::RequireAppleSilicon, Verdict::Allowed, Verdict::Denied),
+        (PlatformPolicy::Warn, Verdict::Allowed, Verdict::Warn),
+        (PlatformPolicy::Any, Verdict::Allowed, Verdict::Allowed),
+    ];
+    for (policy, on_apple, elsewhere) in cases {
+        assert_eq!(policy.evaluate(&apple), on_apple, "{}", policy);
+        assert_eq!(policy.evaluate(&other), elsewhere, "{}", policy);
+    }
+    assert_eq!(PlatformPolicy::default(), PlatformPolicy::RequireAppleSilicon);
+}
+
+#[test]
+fn policies_parse_from_their_names_only() {
+    for policy in [PlatformPolicy::RequireAppleSilicon, PlatformPolicy::Warn, PlatformPolicy::Any] {
+        assert_eq!(policy.to_string().parse(), Ok(policy));
+    }
+    for unknown in ["", "Any", "deny", "apple", " warn"] {
+        let error = unknown.parse::<PlatformPolicy>().unwrap_err();
+        assert!(error.contains("expected require-apple-silicon, warn or any"), "{}", error);
+    }
+}
@@ -0,0 +1,43 @@
+//! Proof-of-work algorithms: known digests, and mining and verifying under each.
+
+mod common;
+
+use mchain::verify::Reason;
+use mchain::{Chain, Consensus, Miner, Pow, Transaction};
+
+use common::hex;
+
+#[test]
+fn digests_match_reference_vectors() {
+    assert_eq!(hex(&Pow::Sha256.hash(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(hex(&Pow::Sha256d.hash(b"abc")), "4f8b42c22dd3729b519ba6f68d2da7cc5b2d606d05daed5ad5128cc03e6c6358");
+    assert_eq!(hex(&Pow::Blake3.hash(b"abc")), "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+    assert_ne!(Pow::Argon2id.hash(b"abc"), Pow::Argon2id.hash(b"abd"));
+    assert_eq!(Pow::Argon2id.hash(b"abc"), Pow::Argon2id.hash(b"abc"));
+}
+
+#[test]
+fn names_round_trip() {
+    for pow in [Pow::Sha256, Pow::Sha256d, Pow::Blake3, Pow::Argon2id] {
+        assert_eq!(pow.to_string().parse::<Pow>(), Ok(pow));
+        assert_eq!(serde_json::to_string(&pow).unwrap(), format!("\"{}\"", pow));
+    }
+}
+
+#[test]
+fn chains_verify_only_under_their_own_algorithm() {
+    for pow in [Pow::Sha256, Pow::Sha256d, Pow::Blake3, Pow::Argon2id] {
+        let miner = Miner::new(4).with_threads(2).with_pow(pow);
+        let mut chain = Chain::new(Vec::new());
+        chain.mine_next(&miner, vec![Transaction::data("genesis")]).unwrap();
+        chain.mine_next(&miner, vec![Transaction::data("next")]).unwrap();
+
+        let consensus = Consensus { min_difficulty: 4, pow, ..Consensus::default() };
+        assert!(chain.verify(&consensus).is_empty(), "{} chain failed to verify", pow);
+
+        let other = if pow == Pow::Sha256 { Pow::Blake3 } else { Pow::Sha256 };
+        let violations = chain.verify(&Consensus { pow: other, ..consensus });
+        let mismatches = violations.iter().filter(|v| matches!(v.reason, Reason::HashMismatch { .. }));
+        assert_eq!(mismatches.count(), 2);
+    }
+}
@@ -0,0 +1,90 @@
+//! Merkle audit paths and self-contained inclusion proofs.
+
+use mchain::merkle::{leaf_hash, proof, root, root_from_proof};
+use mchain::proof::{InclusionProof, ProofError};
+use mchain::{Chain, Miner, Pow, Transaction};
+
+fn leaves(n: usize) -> Vec<[u8; 32]> {
+    (0..n).map(|i| leaf_hash(format!("tx {}", i).as_bytes())).collect()
+}
+
+fn chain(blocks: usize) -> Chain {
+    let miner = Miner::new(4).with_threads(1);
+    let mut chain = Chain::new(Vec::new());
+    for b in 0..blocks {
+        let txs = (0..5).map(|i| Transaction::data(format!("block {} tx {}", b, i))).collect();
+        chain.mine_next(&miner, txs).unwrap();
+    }
+    chain
+}
+
+#[test]
+fn every_audit_path_leads_to_the_root() {
+    for n in 1..=9 {
+        let l = leaves(n);
+        for (i, leaf) in l.iter().enumerate() {
+            assert_eq!(root_from_proof(*leaf, i, n, &proof(&l, i)), Some(root(&l)), "leaf {} of {}", i, n);
+        }
+    }
+}
+
+#[test]
+fn audit_path_of_the_wrong_shape_is_rejected() {
+    let l = leaves(5);
+    let mut path = proof(&l, 2);
+    assert_eq!(root_from_proof(l[2], 2, 9, &path), None);
+    path.pop();
+    assert_eq!(root_from_proof(l[2], 2, 5, &path), None);
+    assert_eq!(root_from_proof(l[2], 5, 5, &path), None);
+}
+
+#[test]
+fn proof_checks_out_for_every_item() {
+    let chain = chain(3);
+    let tip = chain.blocks().last().unwrap().hash.clone();
+    for item in 0..5 {
+        let proof = InclusionProof::build(chain.blocks(), Pow::Sha256, 1, item, None).unwrap();
+        assert_eq!(proof.headers.len(), 2);
+        let (checkpoint, hash) = proof.verify(Some(&tip)).unwrap();
+        assert_eq!(checkpoint.index, 2);
+        assert_eq!(hash, tip);
+    }
+    assert!(InclusionProof::build(chain.blocks(), Pow::Sha256, 1, 5, None).is_err());
+    assert!(InclusionProof::build(chain.blocks(), Pow::Sha256, 3, 0, None).is_err());
+    assert!(InclusionProof::build(chain.blocks(), Pow::Sha256, 2, 0, Some(1)).is_err());
+}
+
+#[test]
+fn proof_survives_a_json_round_trip() {
+    let chain = chain(2);
+    let proof = InclusionProof::build(chain.blocks(), Pow::Sha256, 0, 3, None).unwrap();
+    let loaded: InclusionProof = serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+    assert_eq!(loaded, proof);
+    assert!(loaded.verify(None).is_ok());
+}
+
+#[test]
+fn tampered_proofs_are_rejected() {
+    let chain = chain(3);
+    let proof = InclusionProof::build(chain.blocks(), Pow::Sha256, 0, 2, None).unwrap();
+
+    let mut forged = proof.clone();
+    forged.transaction = Transaction::data("forged");
+    assert!(matches!(forged.verify(None), Err(ProofError::RootMismatch { .. })));
+
+    let mut forged = proof.clone();
+    forged.path.pop();
+    assert_eq!(forged.verify(None), Err(ProofError::PathShape));
+
+    let mut forged = proof.clone();
+    forged.headers.remove(1);
+    assert_eq!(forged.verify(None), Err(ProofError::BrokenLink { index: 2 }));
+
+    let mut forged = proof.clone();
+    forged.headers[0].nonce += 1;
+    let reason = forged.verify(None).unwrap_err();
+    assert!(matches!(reason, ProofError::InsufficientWork { index: 0 } | ProofError::BrokenLink { index: 1 }));
+
+    let wrong = "0".repeat(64);
+    assert!(matches!(proof.verify(Some(&wrong)), Err(ProofError::UntrustedCheckpoint { .. })));
+}
@@ -0,0 +1,96 @@
+//! Coinbase transactions and the block reward schedule.
+
+use mchain::amount::{format_coins, parse_coins, COIN};
+use mchain::verify::Reason;
+use mchain::{Chain, Consensus, Miner, Output, Reward, Transaction};
+
+fn consensus(reward: &Reward) -> Consensus {
+    Consensus { min_difficulty: 4, reward: Some(reward.clone()), ..Consensus::default() }
+}
+
+/// Mine one block holding `transactions` on a fresh chain and return its violations.
+fn verify_genesis(reward: &Reward, transactions: Vec<Transaction>) -> Vec<Reason> {
+    let mut chain = Chain::new(Vec::new());
+    chain.mine_next(&Miner::new(4).with_threads(1), transactions).unwrap();
+    chain.verify(&consensus(reward)).into_iter().map(|v| v.reason).collect()
+}
+
+fn pay(address: &str, amount: u64) -> Output {
+    Output { address: address.to_string(), amount }
+}
+
+#[test]
+fn subsidy_halves_on_schedule() {
+    let reward = Reward { initial_subsidy: 50 * COIN, halving_interval: 10 };
+    assert_eq!(reward.subsidy(0), 50 * COIN);
+    assert_eq!(reward.subsidy(9), 50 * COIN);
+    assert_eq!(reward.subsidy(10), 25 * COIN);
+    assert_eq!(reward.subsidy(25), 25 * COIN / 2);
+    assert_eq!(reward.subsidy(10 * 64), 0);
+    assert_eq!(reward.subsidy(u64::MAX), 0);
+}
+
+#[test]
+fn coin_amounts_round_trip() {
+    assert_eq!(parse_coins("12.5"), Ok(1_250_000_000));
+    assert_eq!(parse_coins("0.00000001"), Ok(1));
+    assert_eq!(parse_coins(".5"), Ok(COIN / 2));
+    assert!(parse_coins("0.000000001").is_err());
+    assert!(parse_coins("1e3").is_err());
+    assert!(parse_coins("").is_err());
+    assert!(parse_coins("184467440738").is_err());
+    assert_eq!(format_coins(1_250_000_000), "12.5");
+    assert_eq!(format_coins(50 * COIN), "50");
+    assert_eq!(format_coins(1), "0.00000001");
+}
+
+#[test]
+fn rewarded_chain_verifies() {
+    let reward = Reward { initial_subsidy: 8 * COIN, halving_interval: 2 };
+    let miner = Miner::new(4).with_threads(1);
+    let mut chain = Chain::new(Vec::new());
+    for height in 0..5 {
+        let txs = vec![reward.coinbase(height, 0, Some("miner")), Transaction::data("payload")];
+        chain.mine_next(&miner, txs).unwrap();
+    }
+    let paid: Vec<_> = chain.blocks().iter().map(|b| b.body.transactions().unwrap()[0].outputs[0].amount).collect();
+    assert_eq!(paid, [8 * COIN, 8 * COIN, 4 * COIN, 4 * COIN, 2 * COIN]);
+    assert!(chain.verify(&consensus(&reward)).is_empty());
+}
+
+#[test]
+fn coinbase_above_the_subsidy_is_rejected() {
+    let reward = Reward::default();
+    let greedy = Transaction::coinbase(0, vec![pay("a", reward.subsidy(0)), pay("b", 1)]);
+    let allowed = reward.subsidy(0);
+    assert_eq!(verify_genesis(&reward, vec![greedy]), [Reason::ExcessiveCoinbase { claimed: allowed + 1, allowed }]);
+
+    let overflowing = Transaction::coinbase(0, vec![pay("a", u64::MAX), pay("b", u64::MAX)]);
+    assert!(matches!(verify_genesis(&reward, vec![overflowing])[..], [Reason::ExcessiveCoinbase { .. }]));
+}
+
+#[test]
+fn every_block_needs_its_own_coinbase_first() {
+    let reward = Reward::default();
+    assert_eq!(verify_genesis(&reward, vec![Transaction::data("no reward")]), [Reason::MissingCoinbase]);
+
+    let stale = reward.coinbase(7, 0, Some("miner"));
+    assert_eq!(verify_genesis(&reward, vec![stale]), [Reason::CoinbaseHeight { recorded: 7 }]);
+
+    let twice = vec![reward.coinbase(0, 0, Some("miner")), reward.coinbase(0, 0, Some("miner"))];
+    assert!(verify_genesis(&reward, twice).contains(&Reason::MisplacedCoinbase { item: 1 }));
+}
+
+#[test]
+fn only_a_coinbase_creates_coins() {
+    let reward = Reward::default();
+    let printer = Transaction { outputs: vec![pay("me", COIN)], ..Transaction::data("free money") };
+    let txs = vec![reward.coinbase(0, 0, None), printer];
+    assert_eq!(verify_genesis(&reward, txs), [Reason::Overspend { item: 1, spent: COIN, available: 0 }]);
+
+    // Chains created before rewards allow no subsidy at all.
+    let mut chain = Chain::new(Vec::new());
+    chain.mine_next(&Miner::new(4).with_threads(1), vec![reward.coinbase(0, 0, Some("miner"))]).unwrap();
+    let reasons: Vec<_> = chain.verify(&Consensus { min_difficulty: 4, ..Consensus::default() }).into_iter().map(|v| v.reason).collect();
+    assert_eq!(reasons, [Reason::ExcessiveCoinbase { claimed: reward.subsidy(0), allowed: 0 }]);
+}
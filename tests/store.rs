@@ -0,0 +1,462 @@
+//! Block stores: what they load, what they report, and what they repair.
+//!
+//! Interrupting a save must never leave the store unloadable. The test re-runs this
+//! binary as a child that saves large blocks in a loop, kills it at varying points, and
+//! checks that what is on disk always loads as a clean prefix.
+
+mod common;
+
+use std::env;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use mchain::store::{self, LoadIssue, SEGMENT_BLOCKS};
+use mchain::{Block, BlockStore, Body, DirStore, Error, LogStore, MemoryStore};
+
+use common::scratch_dir;
+
+const CHILD_DIR_VAR: &str = "MCHAIN_TEST_WRITER_DIR";
+
+fn block(index: u64) -> Block {
+    // Large enough that a kill often lands mid-write.
+    Block { body: Body::Legacy { data: "x".repeat(4 << 20) }, ..small_block(index) }
+}
+
+fn small_block(index: u64) -> Block {
+    Block {
+        version: 1,
+        index,
+        timestamp: index,
+        body: Body::Legacy { data: format!("block {}", index) },
+        nonce: 0,
+        extra_nonce: 0,
+        difficulty: Some(0),
+        previous_hash: "0".to_string(),
+        merkle_root: None,
+        hash: format!("{:064x}", index),
+        mining_duration_ms: 0,
+        platform: None,
+    }
+}
+
+/// Not a real test: the body of the child process spawned below, ignored so normal runs
+/// skip it.
+#[test]
+#[ignore]
+fn child_writer() {
+    let Ok(dir) = env::var(CHILD_DIR_VAR) else {
+        return;
+    };
+    let mut store = DirStore::new(dir);
+    let mut next = store.load().expect("store loads before writing").len() as u64;
+    loop {
+        store.append(&block(next)).expect("append");
+        next += 1;
+    }
+}
+
+#[test]
+fn interrupted_saves_leave_a_consistent_prefix() {
+    let dir = scratch_dir("interrupt");
+    let store = DirStore::new(&dir);
+
+    for round in 0..12 {
+        let mut child = Command::new(env::current_exe().unwrap())
+            .args(["--ignored", "--exact", "child_writer", "--nocapture"])
+            .env(CHILD_DIR_VAR, &dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        thread::sleep(Duration::from_millis(20 + round * 17));
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let report = store.scan().unwrap();
+        assert!(report.is_clean(), "round {}: {:?}", round, report.issues);
+        for (position, block) in report.blocks.iter().enumerate() {
+            assert_eq!(block.index, position as u64);
+            assert!(matches!(&block.body, Body::Legacy { data } if data.len() == 4 << 20));
+        }
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn leftover_temp_file_is_ignored_and_overwritten() {
+    let dir = scratch_dir("leftover");
+    let store = DirStore::new(&dir);
+    store.save(&block(0)).unwrap();
+
+    let target = store.block_path(1);
+    std::fs::write(mchain::atomic::temp_path(&target), b"{\"index\": 1, \"trunc").unwrap();
+    assert_eq!(store.load().unwrap().len(), 1);
+
+    store.save(&block(1)).unwrap();
+    assert_eq!(store.load().unwrap().len(), 2);
+    assert!(!mchain::atomic::temp_path(&target).exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// A directory store holding blocks `0..count`.
+fn dir_store(name: &str, count: u64) -> (PathBuf, DirStore) {
+    let dir = scratch_dir(name);
+    let mut store = DirStore::new(&dir);
+    for index in 0..count {
+        store.append(&small_block(index)).unwrap();
+    }
+    (dir, store)
+}
+
+fn issues(store: &dyn BlockStore) -> Vec<LoadIssue> {
+    match store.load() {
+        Err(Error::Load(e)) => e.issues,
+        other => panic!("expected a load error, got {:?}", other.map(|blocks| blocks.len())),
+    }
+}
+
+#[test]
+fn blocks_load_in_index_order_not_file_name_order() {
+    let (dir, store) = dir_store("dir-order", 12);
+    let indices: Vec<u64> = store.load().unwrap().iter().map(|b| b.index).collect();
+    assert_eq!(indices, (0..12).collect::<Vec<_>>());
+    assert_eq!(store.tip().unwrap().unwrap().index, 11);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_missing_block_is_reported_as_a_gap() {
+    let (dir, store) = dir_store("dir-gap", 6);
+    std::fs::remove_file(store.block_path(2)).unwrap();
+    std::fs::remove_file(store.block_path(3)).unwrap();
+
+    assert!(matches!(&issues(&store)[..], [LoadIssue::Gap { index: 2 }, LoadIssue::Gap { index: 3 }]));
+    let report = store.scan().unwrap();
+    assert_eq!(report.blocks.iter().map(|b| b.index).collect::<Vec<_>>(), [0, 1, 4, 5]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_duplicated_index_keeps_the_correctly_named_copy() {
+    let (dir, store) = dir_store("dir-duplicate", 4);
+    let mut copy = small_block(2);
+    copy.nonce = 7;
+    let stray = dir.join("block_2_copy.json");
+    std::fs::write(&stray, serde_json::to_string(&copy).unwrap()).unwrap();
+
+    let found = issues(&store);
+    assert_eq!(found.len(), 2, "{:?}", found);
+    assert!(matches!(&found[0], LoadIssue::NameMismatch { path, index: 2 } if *path == stray));
+    match &found[1] {
+        LoadIssue::Duplicate { index: 2, paths } => {
+            assert_eq!(paths.len(), 2);
+            assert!(paths.contains(&store.block_path(2)) && paths.contains(&stray));
+        }
+        other => panic!("expected a duplicate, got {:?}", other),
+    }
+    assert_eq!(store.scan().unwrap().blocks[2], small_block(2));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_block_under_another_index_name_is_a_mismatch() {
+    let (dir, store) = dir_store("dir-mismatch", 3);
+    std::fs::rename(store.block_path(2), store.block_path(5)).unwrap();
+
+    let found = issues(&store);
+    assert!(matches!(&found[..], [LoadIssue::NameMismatch { path, index: 2 }] if *path == store.block_path(5)));
+    assert!(matches!(store.load(), Err(e) if e.to_string().contains("block_5.json")));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn repair_quarantines_a_corrupt_block_and_everything_after_it() {
+    let (dir, mut store) = dir_store("dir-corrupt", 6);
+    let bytes = std::fs::read(store.block_path(3)).unwrap();
+    std::fs::write(store.block_path(3), &bytes[..bytes.len() / 2]).unwrap();
+
+    let found = issues(&store);
+    assert!(matches!(&found[0], LoadIssue::Corrupt { path, .. } if *path == store.block_path(3)), "{:?}", found);
+    assert!(matches!(&found[1..], [LoadIssue::Gap { index: 3 }]));
+
+    let moved = store.repair().unwrap();
+    let sources: Vec<PathBuf> = moved.iter().map(|(from, _)| from.clone()).collect();
+    assert_eq!(sources, [store.block_path(3), store.block_path(4), store.block_path(5)]);
+    for (from, to) in &moved {
+        assert!(!from.exists());
+        assert!(to.starts_with(dir.join("corrupt")));
+    }
+    assert_eq!(store.load().unwrap(), (0..3).map(small_block).collect::<Vec<_>>());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn migrating_keeps_quarantined_files_until_the_chain_is_reset() {
+    let (dir, mut store) = dir_store("migrate-quarantine", 6);
+    std::fs::write(store.block_path(3), "{").unwrap();
+    let moved = store.repair().unwrap();
+    assert_eq!(moved.len(), 3);
+
+    assert_eq!(store::migrate(&dir, mchain::Backend::Log).unwrap(), 3);
+    assert!(!store.block_path(0).exists());
+    for (_, to) in &moved {
+        assert!(to.exists(), "{} was deleted by the migration", to.display());
+    }
+
+    let mut log = LogStore::new(&dir);
+    assert_eq!(log.load().unwrap(), (0..3).map(small_block).collect::<Vec<_>>());
+    assert!(store::reset_chain(&dir, &mut log).unwrap());
+    assert!(!dir.exists());
+}
+
+/// Write `indices` straight into segment `segment` of `store`, one record per line.
+fn write_segment(store: &LogStore, segment: u64, indices: std::ops::Range<u64>) {
+    std::fs::create_dir_all(store.dir()).unwrap();
+    let records: String = indices.map(|i| serde_json::to_string(&small_block(i)).unwrap() + "\n").collect();
+    std::fs::write(store.segment_path(segment), records).unwrap();
+}
+
+fn indices(blocks: &[Block]) -> Vec<u64> {
+    blocks.iter().map(|b| b.index).collect()
+}
+
+#[test]
+fn log_store_rolls_over_into_a_new_segment() {
+    let dir = scratch_dir("log-rollover");
+    let mut store = LogStore::new(&dir);
+    write_segment(&store, 0, 0..SEGMENT_BLOCKS - 1);
+    for index in SEGMENT_BLOCKS - 1..SEGMENT_BLOCKS + 2 {
+        store.append(&small_block(index)).unwrap();
+    }
+    assert!(store.append(&small_block(SEGMENT_BLOCKS + 3)).is_err());
+
+    let tail = std::fs::read_to_string(store.segment_path(1)).unwrap();
+    assert_eq!(tail.lines().count(), 2);
+    assert_eq!(store.len().unwrap(), SEGMENT_BLOCKS + 2);
+    assert_eq!(store.get(SEGMENT_BLOCKS - 1).unwrap(), Some(small_block(SEGMENT_BLOCKS - 1)));
+    assert_eq!(store.get(SEGMENT_BLOCKS).unwrap(), Some(small_block(SEGMENT_BLOCKS)));
+    assert_eq!(store.get(SEGMENT_BLOCKS + 2).unwrap(), None);
+    assert_eq!(store.tip().unwrap(), Some(small_block(SEGMENT_BLOCKS + 1)));
+    assert_eq!(indices(&store.load().unwrap()), (0..SEGMENT_BLOCKS + 2).collect::<Vec<_>>());
+
+    let late = small_block(SEGMENT_BLOCKS + 1);
+    assert_eq!(store.get_by_hash(&late.hash).unwrap(), Some(late));
+    assert_eq!(store.get_by_hash(&small_block(5).hash).unwrap(), Some(small_block(5)));
+    assert_eq!(store.get_by_hash(&small_block(SEGMENT_BLOCKS + 2).hash).unwrap(), None);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_torn_last_record_is_reported_and_cut_off() {
+    let dir = scratch_dir("log-torn");
+    let mut store = LogStore::new(&dir);
+    for index in 0..4 {
+        store.append(&small_block(index)).unwrap();
+    }
+    let record = serde_json::to_string(&small_block(4)).unwrap();
+    let torn = &record[..record.len() / 2];
+    let mut contents = std::fs::read_to_string(store.segment_path(0)).unwrap();
+    contents.push_str(torn);
+    std::fs::write(store.segment_path(0), contents).unwrap();
+
+    let found = issues(&store);
+    assert!(matches!(&found[..], [LoadIssue::CorruptRecord { line: 5, .. }]), "{:?}", found);
+    assert_eq!(indices(&store.scan().unwrap().blocks), [0, 1, 2, 3]);
+
+    let moved = store.repair().unwrap();
+    assert_eq!(moved.len(), 1);
+    assert_eq!(moved[0].0, store.segment_path(0));
+    assert_eq!(std::fs::read_to_string(&moved[0].1).unwrap(), torn);
+    assert_eq!(indices(&store.load().unwrap()), [0, 1, 2, 3]);
+    store.append(&small_block(4)).unwrap();
+    assert_eq!(store.tip().unwrap(), Some(small_block(4)));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_complete_but_unterminated_last_record_is_kept() {
+    let dir = scratch_dir("log-unterminated");
+    let mut store = LogStore::new(&dir);
+    write_segment(&store, 0, 0..3);
+    let mut contents = std::fs::read_to_string(store.segment_path(0)).unwrap();
+    contents.pop();
+    std::fs::write(store.segment_path(0), contents).unwrap();
+
+    assert_eq!(indices(&store.load().unwrap()), [0, 1, 2]);
+    store.append(&small_block(3)).unwrap();
+    assert_eq!(indices(&store.load().unwrap()), [0, 1, 2, 3]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn repair_cuts_the_log_at_a_bad_record_or_a_missing_segment() {
+    let dir = scratch_dir("log-repair");
+    let mut store = LogStore::new(&dir);
+    write_segment(&store, 0, 0..SEGMENT_BLOCKS);
+    write_segment(&store, 2, 2 * SEGMENT_BLOCKS..2 * SEGMENT_BLOCKS + 3);
+
+    let found = issues(&store);
+    assert!(matches!(&found[..], [LoadIssue::Gap { index }] if *index == SEGMENT_BLOCKS), "{:?}", found);
+    let moved = store.repair().unwrap();
+    assert_eq!(moved.len(), 1);
+    assert_eq!(moved[0].0, store.segment_path(2));
+    assert_eq!(store.load().unwrap().len() as u64, SEGMENT_BLOCKS);
+
+    // A record out of place cuts the segment there, keeping the records before it.
+    let mut contents = std::fs::read_to_string(store.segment_path(0)).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    contents = [&lines[..10], &lines[11..]].concat().join("\n") + "\n";
+    std::fs::write(store.segment_path(0), contents).unwrap();
+    let found = issues(&store);
+    assert!(matches!(&found[0], LoadIssue::OutOfOrder { line: 11, expected: 10, found: 11, .. }), "{:?}", found);
+    store.repair().unwrap();
+    assert_eq!(indices(&store.load().unwrap()), (0..10).collect::<Vec<_>>());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn log_store_truncates_across_segments() {
+    let dir = scratch_dir("log-truncate");
+    let mut store = LogStore::new(&dir);
+    write_segment(&store, 0, 0..SEGMENT_BLOCKS);
+    write_segment(&store, 1, SEGMENT_BLOCKS..2 * SEGMENT_BLOCKS);
+    write_segment(&store, 2, 2 * SEGMENT_BLOCKS..2 * SEGMENT_BLOCKS + 5);
+
+    assert!(store.truncate(3 * SEGMENT_BLOCKS).unwrap().is_empty());
+    let removed = store.truncate(SEGMENT_BLOCKS + 10).unwrap();
+    assert_eq!(indices(&removed), (SEGMENT_BLOCKS + 10..2 * SEGMENT_BLOCKS + 5).collect::<Vec<_>>());
+    assert!(!store.segment_path(2).exists());
+    assert_eq!(store.len().unwrap(), SEGMENT_BLOCKS + 10);
+
+    // Cutting at a segment boundary deletes the segment that would be left empty.
+    assert_eq!(store.truncate(SEGMENT_BLOCKS).unwrap().len(), 10);
+    assert!(!store.segment_path(1).exists());
+    assert_eq!(store.tip().unwrap(), Some(small_block(SEGMENT_BLOCKS - 1)));
+
+    assert_eq!(indices(&store.truncate(0).unwrap()), (0..SEGMENT_BLOCKS).collect::<Vec<_>>());
+    assert!(store.is_empty().unwrap());
+    store.append(&small_block(0)).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn memory_store_holds_a_contiguous_chain() {
+    let mut store = MemoryStore::new();
+    assert!(store.is_empty().unwrap());
+    assert_eq!(store.tip().unwrap(), None);
+    for index in 0..5 {
+        store.append(&small_block(index)).unwrap();
+    }
+    assert!(matches!(store.append(&small_block(7)), Err(Error::OutOfSequence { expected: 5, found: 7 })));
+
+    assert_eq!(store.len().unwrap(), 5);
+    assert_eq!(store.get(2).unwrap(), Some(small_block(2)));
+    assert_eq!(store.get(5).unwrap(), None);
+    assert_eq!(store.tip().unwrap(), Some(small_block(4)));
+    assert_eq!(store.get_by_hash(&small_block(3).hash).unwrap(), Some(small_block(3)));
+    assert_eq!(indices(&store.load().unwrap()), [0, 1, 2, 3, 4]);
+    assert!(store.scan().unwrap().is_clean());
+    assert!(store.repair().unwrap().is_empty());
+
+    assert_eq!(indices(&store.truncate(3).unwrap()), [3, 4]);
+    assert_eq!(store.get_by_hash(&small_block(3).hash).unwrap(), None);
+    assert!(store.truncate(9).unwrap().is_empty());
+    store.append(&small_block(3)).unwrap();
+    assert_eq!(store.get_by_hash(&small_block(3).hash).unwrap(), Some(small_block(3)));
+
+    assert!(store.reset().unwrap());
+    assert!(!store.reset().unwrap());
+    assert_eq!(store.get_by_hash(&small_block(0).hash).unwrap(), None);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn sqlite_store_appends_looks_up_truncates_and_repairs() {
+    use mchain::store::SqliteStore;
+
+    let dir = scratch_dir("sqlite-store");
+    let mut store = SqliteStore::new(&dir).unwrap();
+    assert!(store.is_empty().unwrap());
+    assert!(!store.path().exists());
+    for index in 0..6 {
+        store.append(&small_block(index)).unwrap();
+    }
+    assert!(matches!(store.append(&small_block(4)), Err(Error::OutOfSequence { expected: 6, found: 4 })));
+
+    assert_eq!(store.len().unwrap(), 6);
+    assert_eq!(store.get(3).unwrap(), Some(small_block(3)));
+    assert_eq!(store.get(6).unwrap(), None);
+    assert_eq!(store.tip().unwrap(), Some(small_block(5)));
+    assert_eq!(store.get_by_hash(&small_block(2).hash).unwrap(), Some(small_block(2)));
+    assert_eq!(store.get_by_hash(&small_block(9).hash).unwrap(), None);
+
+    assert!(store.truncate(6).unwrap().is_empty());
+    assert_eq!(indices(&store.truncate(4).unwrap()), [4, 5]);
+    assert_eq!(store.get_by_hash(&small_block(4).hash).unwrap(), None);
+    store.append(&small_block(4)).unwrap();
+    assert_eq!(indices(&store.load().unwrap()), [0, 1, 2, 3, 4]);
+
+    // A row that no longer parses is deleted along with every row above it.
+    let conn = rusqlite::Connection::open(store.path()).unwrap();
+    conn.execute("UPDATE blocks SET body = '{\"index\": 2, \"trunc' WHERE height = 2", []).unwrap();
+    let found = issues(&store);
+    assert!(matches!(&found[..], [LoadIssue::CorruptRow { height: 2, .. }]), "{:?}", found);
+    let moved = store.repair().unwrap();
+    assert_eq!(moved.len(), 1);
+    assert_eq!(std::fs::read_to_string(&moved[0].1).unwrap().lines().count(), 3);
+    assert_eq!(indices(&store.load().unwrap()), [0, 1]);
+
+    assert!(store.reset().unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn migrating_to_sqlite_and_back_keeps_every_block() {
+    use mchain::store::ChainConfig;
+    use mchain::Backend;
+
+    let (dir, store) = dir_store("sqlite-migrate", 12);
+    let original = store.load().unwrap();
+
+    assert_eq!(store::migrate(&dir, Backend::Sqlite).unwrap(), 12);
+    assert_eq!(ChainConfig::load(&dir).unwrap().unwrap().backend, Backend::Sqlite);
+    assert!(!store.block_path(0).exists());
+    let sqlite = Backend::Sqlite.open(&dir).unwrap();
+    let migrated = sqlite.load().unwrap();
+    assert_eq!(indices(&migrated), indices(&original));
+    assert_eq!(migrated.iter().map(|b| &b.hash).collect::<Vec<_>>(), original.iter().map(|b| &b.hash).collect::<Vec<_>>());
+    assert_eq!(sqlite.get_by_hash(&original[7].hash).unwrap().map(|b| b.index), Some(7));
+    drop(sqlite);
+
+    assert!(store::migrate(&dir, Backend::Sqlite).is_err());
+    assert_eq!(store::migrate(&dir, Backend::Json).unwrap(), 12);
+    assert_eq!(store.load().unwrap(), original);
+    assert!(!dir.join("blocks.sqlite").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn chain_names_stay_inside_the_data_directory() {
+    let dir = scratch_dir("chain-names");
+    assert_eq!(store::chain_dir(&dir, None).unwrap(), dir);
+    assert_eq!(store::chain_dir(&dir, Some("test-net_2")).unwrap(), dir.join("chains").join("test-net_2"));
+    for name in ["../x", "a/b", "", ".", "..", "a b", "/abs", "a\\b"] {
+        assert!(!store::is_valid_chain_name(name), "{:?}", name);
+        assert!(matches!(store::chain_dir(&dir, Some(name)), Err(Error::InvalidChainName(n)) if n == name));
+    }
+
+    assert!(store::chain_names(&dir).unwrap().is_empty());
+    for name in ["main", "bench", "test-net_2"] {
+        std::fs::create_dir_all(store::chain_dir(&dir, Some(name)).unwrap()).unwrap();
+    }
+    // Only directories with valid names are chains.
+    std::fs::create_dir_all(dir.join("chains").join("not a chain")).unwrap();
+    std::fs::write(dir.join("chains").join("stray"), b"").unwrap();
+    assert_eq!(store::chain_names(&dir).unwrap(), ["bench", "main", "test-net_2"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
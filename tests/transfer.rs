@@ -0,0 +1,154 @@
+//! Signed transfers between wallets, and how verification catches bad ones.
+
+mod common;
+
+use mchain::amount::COIN;
+use mchain::verify::Reason;
+use mchain::{Chain, Consensus, Keypair, Output, Reward, Transaction, UtxoSet};
+
+use common::address;
+
+struct Fixture {
+    chain: Chain,
+    reward: Reward,
+    alice: Keypair,
+    bob: Keypair,
+}
+
+impl Fixture {
+    /// A chain whose genesis block pays 50 coins to alice.
+    fn new() -> Self {
+        let (alice, bob) = (common::alice(), common::bob());
+        let mut fixture = Fixture { chain: Chain::new(Vec::new()), reward: Reward::default(), alice, bob };
+        fixture.mine(Vec::new());
+        fixture
+    }
+
+    fn utxos(&self) -> UtxoSet {
+        UtxoSet::from_blocks(self.chain.blocks())
+    }
+
+    /// Mine `txs` with a coinbase paying alice the subsidy plus `fees`.
+    fn mine_with_fees(&mut self, txs: Vec<Transaction>, fees: u64) {
+        common::mine(&mut self.chain, &self.alice, txs, fees);
+    }
+
+    fn mine(&mut self, txs: Vec<Transaction>) {
+        self.mine_with_fees(txs, 0);
+    }
+
+    fn violations(&self) -> Vec<Reason> {
+        let consensus = Consensus { min_difficulty: 4, reward: Some(self.reward.clone()), ..Consensus::default() };
+        self.chain.verify(&consensus).into_iter().map(|v| v.reason).collect()
+    }
+}
+
+#[test]
+fn payment_moves_coins_and_pays_its_fee_to_the_miner() {
+    let mut f = Fixture::new();
+    let tx = f.utxos().pay(&f.alice, &address(&f.bob), 30 * COIN, COIN).unwrap();
+    assert_eq!(tx.outputs.len(), 2);
+    f.mine_with_fees(vec![tx], COIN);
+    assert!(f.violations().is_empty());
+
+    let utxos = f.utxos();
+    assert_eq!(utxos.balance(&address(&f.bob)), 30 * COIN);
+    // 19 coins of change plus the second block's subsidy and fee.
+    assert_eq!(utxos.balance(&address(&f.alice)), 19 * COIN + 51 * COIN);
+    assert!(f.utxos().pay(&f.bob, &address(&f.alice), 31 * COIN, 0).is_err());
+}
+
+#[test]
+fn claiming_more_fee_than_left_is_rejected() {
+    let mut f = Fixture::new();
+    let tx = f.utxos().pay(&f.alice, &address(&f.bob), 30 * COIN, COIN).unwrap();
+    f.mine_with_fees(vec![tx], 2 * COIN);
+    let allowed = 51 * COIN;
+    assert_eq!(f.violations(), [Reason::ExcessiveCoinbase { claimed: allowed + COIN, allowed }]);
+}
+
+#[test]
+fn tampered_or_unsigned_inputs_are_rejected() {
+    let f = Fixture::new();
+    let mut tx = f.utxos().pay(&f.alice, &address(&f.bob), 30 * COIN, 0).unwrap();
+    tx.outputs[0].amount += 1;
+    tx.outputs[1].amount -= 1;
+    let mut unsigned = f.utxos().pay(&f.alice, &address(&f.bob), 20 * COIN, 0).unwrap();
+    unsigned.inputs[0].signature = None;
+
+    for tx in [tx, unsigned] {
+        let mut f = Fixture::new();
+        f.mine(vec![tx]);
+        assert_eq!(f.violations(), [Reason::BadSignature { item: 1, input: 0 }]);
+    }
+}
+
+#[test]
+fn only_the_owner_can_spend_an_output() {
+    let mut f = Fixture::new();
+    let alices = *f.utxos().owned_by(&address(&f.alice)).next().unwrap().0;
+    let mut theft = Transaction::transfer(&[alices], f.bob.public_key(), vec![Output {
+        address: address(&f.bob),
+        amount: 50 * COIN,
+    }]);
+    theft.sign(&f.bob);
+    f.mine(vec![theft]);
+    assert_eq!(f.violations(), [Reason::WrongKey { item: 1, input: 0 }]);
+}
+
+#[test]
+fn an_output_can_only_be_spent_once() {
+    let mut f = Fixture::new();
+    let utxos = f.utxos();
+    let first = utxos.pay(&f.alice, &address(&f.bob), 10 * COIN, 0).unwrap();
+    let second = utxos.pay(&f.alice, &address(&f.bob), 20 * COIN, 0).unwrap();
+    f.mine(vec![first]);
+    assert!(f.violations().is_empty());
+    f.mine(vec![second.clone()]);
+    let outpoint = second.inputs[0].outpoint;
+    assert_eq!(f.violations(), [Reason::MissingInput { item: 1, input: 0, outpoint }]);
+
+    // Spending one output twice in a transaction doesn't double its value either.
+    let mut f = Fixture::new();
+    let mut twice = Transaction::transfer(&[outpoint, outpoint], f.alice.public_key(), vec![Output {
+        address: address(&f.bob),
+        amount: 100 * COIN,
+    }]);
+    twice.sign(&f.alice);
+    f.mine(vec![twice]);
+    let violations = f.violations();
+    assert!(violations.contains(&Reason::DuplicateInput { item: 1, input: 1 }));
+    assert!(violations.contains(&Reason::Overspend { item: 1, spent: 100 * COIN, available: 50 * COIN }));
+}
+
+#[test]
+fn transaction_survives_a_json_round_trip() {
+    let f = Fixture::new();
+    let tx = f.utxos().pay(&f.alice, &address(&f.bob), 5 * COIN, 0).unwrap();
+    let json = serde_json::to_string(&tx).unwrap();
+    let loaded: Transaction = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, tx);
+    assert_eq!(loaded.id(), tx.id());
+    assert!(serde_json::from_str::<Transaction>(&json.replace("\"txid\":\"", "\"txid\":\"zz")).is_err());
+}
+
+#[test]
+fn one_payment_can_spend_outputs_of_several_keys() {
+    let mut f = Fixture::new();
+    let carol = Keypair::from_secret([3; 32]);
+    let tx = f.utxos().pay(&f.alice, &address(&carol), 40 * COIN, 0).unwrap();
+    f.mine(vec![tx]);
+
+    // Carol's 40 and alice's 60 left after the second block's reward.
+    let keys = [f.alice.clone(), carol.clone()];
+    let tx = f.utxos().pay_from(&keys, &address(&f.bob), 100 * COIN, 0).unwrap();
+    let signers: Vec<_> = tx.inputs.iter().map(|input| input.public_key).collect();
+    assert!(signers.contains(&carol.public_key()) && signers.contains(&f.alice.public_key()));
+    f.mine(vec![tx]);
+    assert!(f.violations().is_empty());
+
+    let utxos = f.utxos();
+    assert_eq!(utxos.balance(&address(&f.bob)), 100 * COIN);
+    assert_eq!(utxos.balance(&address(&carol)), 0);
+    assert!(utxos.pay_from(&[], &address(&f.bob), COIN, 0).is_err());
+}
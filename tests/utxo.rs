@@ -0,0 +1,66 @@
+//! Keeping the persisted unspent output set in step with the stored blocks.
+
+mod common;
+
+use mchain::amount::COIN;
+use mchain::{BlockStore, Chain, DirStore, Keypair, Transaction, UtxoSet};
+
+use common::{alice, bob, scratch_dir};
+
+/// Mine `count` blocks onto `chain` and `store`, each paying the reward to `miner`.
+fn mine(chain: &mut Chain, store: &mut dyn BlockStore, miner: &Keypair, count: u64) {
+    for _ in 0..count {
+        let data = Transaction::data(format!("block {}", chain.next_index()));
+        store.append(&common::mine(chain, miner, vec![data], 0)).unwrap();
+    }
+}
+
+#[test]
+fn catch_up_applies_only_new_blocks_and_survives_a_reload() {
+    let dir = scratch_dir("utxo-catch-up");
+    let mut store = DirStore::new(&dir);
+    let mut chain = Chain::new(Vec::new());
+    let alice = alice();
+    mine(&mut chain, &mut store, &alice, 3);
+
+    let mut utxos = UtxoSet::load(&dir).unwrap().unwrap_or_default();
+    assert_eq!(utxos.catch_up(&store).unwrap(), 3);
+    utxos.save(&dir).unwrap();
+    assert!(store.scan().unwrap().is_clean(), "utxo.json is not a block file");
+
+    mine(&mut chain, &mut store, &alice, 2);
+    let mut reloaded = UtxoSet::load(&dir).unwrap().unwrap();
+    assert_eq!(reloaded, utxos);
+    assert_eq!(reloaded.catch_up(&store).unwrap(), 2);
+    assert_eq!(reloaded.catch_up(&store).unwrap(), 0);
+    assert_eq!(reloaded, UtxoSet::from_blocks(chain.blocks()));
+    assert_eq!(reloaded.height(), 5);
+    assert_eq!(reloaded.balance(&alice.address().to_string()), 250 * COIN);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn catch_up_starts_over_when_the_chain_was_replaced() {
+    let dir = scratch_dir("utxo-replaced");
+    let mut store = DirStore::new(&dir);
+    let (alice, bob) = (alice(), bob());
+    mine(&mut Chain::new(Vec::new()), &mut store, &alice, 2);
+    let mut utxos = UtxoSet::new();
+    utxos.catch_up(&store).unwrap();
+
+    // A different chain of the same length: the recorded tip is gone.
+    store.reset().unwrap();
+    let mut chain = Chain::new(Vec::new());
+    mine(&mut chain, &mut store, &bob, 2);
+    assert_eq!(utxos.catch_up(&store).unwrap(), 2);
+    assert_eq!(utxos, UtxoSet::from_blocks(chain.blocks()));
+    assert_eq!(utxos.balance(&alice.address().to_string()), 0);
+
+    // A shorter one.
+    store.reset().unwrap();
+    let mut chain = Chain::new(Vec::new());
+    mine(&mut chain, &mut store, &alice, 1);
+    assert_eq!(utxos.catch_up(&store).unwrap(), 1);
+    assert_eq!(utxos, UtxoSet::from_blocks(chain.blocks()));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,173 @@
+//! What `verify_blocks` reports about valid chains and tampered copies of them.
+
+use mchain::verify::Reason;
+use mchain::{calculate_hash, verify_blocks, Block, Body, Chain, Consensus, Miner, Retarget, Transaction, Violation};
+
+/// One leading zero hex digit.
+const BITS: usize = 4;
+
+fn consensus() -> Consensus {
+    Consensus { min_difficulty: BITS, ..Consensus::default() }
+}
+
+/// Give `block` a nonce whose hash meets [`BITS`], and that hash.
+fn seal(mut block: Block) -> Block {
+    for nonce in 0.. {
+        block.nonce = nonce;
+        block.hash = calculate_hash(&block).unwrap();
+        if block.hash.starts_with('0') {
+            break;
+        }
+    }
+    block
+}
+
+/// Version-2 blocks carrying a data string each, one second apart.
+fn legacy_chain(count: u64) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    for index in 0..count {
+        let block = Block {
+            version: 2,
+            index,
+            timestamp: 1_749_431_131 + index,
+            body: Body::Legacy { data: format!("block {}", index) },
+            nonce: 0,
+            extra_nonce: 0,
+            difficulty: Some(BITS),
+            previous_hash: blocks.last().map_or("0".to_string(), |b| b.hash.clone()),
+            merkle_root: None,
+            hash: String::new(),
+            mining_duration_ms: 0,
+            platform: None,
+        };
+        blocks.push(seal(block));
+    }
+    blocks
+}
+
+/// Blocks of data-only transactions, as the miner writes them today.
+fn mined_chain(count: u64) -> Vec<Block> {
+    let mut chain = Chain::new(Vec::new());
+    let miner = Miner::new(BITS).with_threads(1);
+    for index in 0..count {
+        chain.mine_next(&miner, vec![Transaction::data(format!("block {}", index))]).unwrap();
+    }
+    chain.blocks().to_vec()
+}
+
+fn reasons(blocks: &[Block]) -> Vec<(usize, Reason)> {
+    verify_blocks(blocks, &consensus()).into_iter().map(|v| (v.position, v.reason)).collect()
+}
+
+fn mismatch(block: &Block) -> Reason {
+    Reason::HashMismatch { computed: calculate_hash(block).unwrap() }
+}
+
+#[test]
+fn untouched_chains_are_valid() {
+    assert!(reasons(&legacy_chain(4)).is_empty());
+    assert!(reasons(&mined_chain(4)).is_empty());
+}
+
+#[test]
+fn a_missing_block_leaves_an_index_gap_and_a_broken_link() {
+    let mut blocks = legacy_chain(4);
+    blocks.remove(2);
+    assert_eq!(verify_blocks(&blocks, &consensus()), [
+        Violation { position: 2, index: 3, reason: Reason::IndexGap { expected: 2 } },
+        Violation { position: 2, index: 3, reason: Reason::BrokenLink { expected: blocks[1].hash.clone() } },
+    ]);
+}
+
+#[test]
+fn genesis_must_point_at_zero() {
+    let mut blocks = legacy_chain(2);
+    blocks[0].previous_hash = "ab".repeat(32);
+    blocks[0] = seal(blocks[0].clone());
+    blocks[1].previous_hash = blocks[0].hash.clone();
+    blocks[1] = seal(blocks[1].clone());
+    assert_eq!(reasons(&blocks), [(0, Reason::BadGenesis { previous_hash: "ab".repeat(32) })]);
+}
+
+#[test]
+fn a_rewritten_previous_hash_breaks_the_link() {
+    let mut blocks = legacy_chain(4);
+    let expected = blocks[1].hash.clone();
+    blocks[2].previous_hash = "cd".repeat(32);
+    assert_eq!(reasons(&blocks), [(2, Reason::BrokenLink { expected }), (2, mismatch(&blocks[2]))]);
+}
+
+#[test]
+fn timestamps_may_not_go_backwards() {
+    let mut blocks = legacy_chain(3);
+    let previous = blocks[1].timestamp;
+    blocks[2].timestamp = previous - 1;
+    assert_eq!(reasons(&blocks), [(2, Reason::TimestampRegression { previous }), (2, mismatch(&blocks[2]))]);
+
+    // Re-mined at the earlier time, only the regression is left.
+    blocks[2] = seal(blocks[2].clone());
+    assert_eq!(reasons(&blocks), [(2, Reason::TimestampRegression { previous })]);
+}
+
+#[test]
+fn tampered_data_or_nonce_no_longer_matches_the_hash() {
+    let mut blocks = legacy_chain(3);
+    blocks[1].body = Body::Legacy { data: "block one".to_string() };
+    blocks[2].nonce += 1;
+    assert_eq!(reasons(&blocks), [(1, mismatch(&blocks[1])), (2, mismatch(&blocks[2]))]);
+
+    // A transaction changed under the header's Merkle root...
+    let mut blocks = mined_chain(2);
+    let Body::Transactions { transactions } = &mut blocks[1].body else { unreachable!() };
+    transactions[0].data = "forged".to_string();
+    let computed = blocks[1].body.merkle_root().unwrap();
+    assert_eq!(reasons(&blocks), [(1, Reason::MerkleMismatch { computed: computed.clone() })]);
+
+    // ...and with the root updated to match, the header hash gives it away.
+    blocks[1].merkle_root = Some(computed);
+    assert_eq!(reasons(&blocks), [(1, mismatch(&blocks[1]))]);
+}
+
+#[test]
+fn every_violation_is_reported_in_one_pass() {
+    let mut blocks = legacy_chain(5);
+    blocks[0].previous_hash = "ef".repeat(32);
+    blocks[1].nonce += 1;
+    let previous = blocks[2].timestamp;
+    blocks[3].timestamp = previous - 1;
+    blocks[4].index = 7;
+
+    assert_eq!(reasons(&blocks), [
+        (0, Reason::BadGenesis { previous_hash: "ef".repeat(32) }),
+        (0, mismatch(&blocks[0])),
+        (1, mismatch(&blocks[1])),
+        (3, Reason::TimestampRegression { previous }),
+        (3, mismatch(&blocks[3])),
+        (4, Reason::IndexGap { expected: 4 }),
+        (4, mismatch(&blocks[4])),
+    ]);
+}
+
+#[test]
+fn blocks_must_record_the_retargeted_difficulty() {
+    // Blocks come far faster than one an hour, so every adjustment raises the difficulty.
+    let consensus = Consensus { min_difficulty: 1, retarget: Some(Retarget::new(BITS, 2, 3600)), ..Consensus::default() };
+    let mut chain = Chain::new(Vec::new());
+    let mut miner = Miner::new(BITS).with_threads(1);
+    for index in 0..4 {
+        miner.set_difficulty(chain.next_difficulty(&consensus).unwrap());
+        chain.mine_next(&miner, vec![Transaction::data(format!("block {}", index))]).unwrap();
+    }
+    assert!(chain.verify(&consensus).is_empty());
+    let expected = chain.next_difficulty(&consensus).unwrap();
+    assert!(expected > BITS);
+
+    // Mined honestly, but at the difficulty from before the last adjustment.
+    miner.set_difficulty(BITS);
+    chain.mine_next(&miner, vec![Transaction::data("block 4")]).unwrap();
+    assert_eq!(chain.verify(&consensus), [Violation {
+        position: 4,
+        index: 4,
+        reason: Reason::WrongDifficulty { expected, recorded: Some(BITS) },
+    }]);
+}
@@ -0,0 +1,139 @@
+//! Keypairs, addresses, HD derivation and the encrypted keystore.
+
+mod common;
+
+use mchain::hd::{self, ExtendedKey, Mnemonic};
+use mchain::wallet::{verify_signature, GAP_LIMIT};
+use mchain::{Address, Keypair, Keystore};
+
+use common::{hex, scratch_dir};
+
+#[test]
+fn address_round_trips_and_catches_typos() {
+    let keypair = Keypair::from_secret([7; 32]);
+    let address = keypair.address();
+    let text = address.to_string();
+    assert!(text.starts_with("mc"));
+    assert_eq!(text.parse::<Address>(), Ok(address));
+    assert!(address.matches(&keypair.public_key()));
+    assert!(!address.matches(&Keypair::from_secret([8; 32]).public_key()));
+
+    // Swapping any one character for another Base58 digit breaks the checksum.
+    for i in 2..text.len() {
+        let mut typo = text.clone().into_bytes();
+        typo[i] = if typo[i] == b'2' { b'3' } else { b'2' };
+        assert!(String::from_utf8(typo).unwrap().parse::<Address>().is_err(), "typo at {}", i);
+    }
+    assert!(text[2..].parse::<Address>().is_err());
+    assert!("mc0OIl".parse::<Address>().is_err());
+}
+
+#[test]
+fn signatures_verify_only_for_their_key_and_message() {
+    let keypair = Keypair::generate().unwrap();
+    let signature = keypair.sign(b"pay bob");
+    assert!(verify_signature(&keypair.public_key(), b"pay bob", &signature));
+    assert!(!verify_signature(&keypair.public_key(), b"pay eve", &signature));
+    assert!(!verify_signature(&Keypair::generate().unwrap().public_key(), b"pay bob", &signature));
+}
+
+#[test]
+fn keystore_unlocks_only_with_its_password() {
+    let keypair = Keypair::generate().unwrap();
+    let keystore = Keystore::seal("alice", &keypair, "correct horse").unwrap();
+    assert_eq!(keystore.address, keypair.address().to_string());
+    assert!(!serde_json::to_string(&keystore).unwrap().contains(&hex(&keypair.secret())));
+    assert_eq!(keystore.unlock("correct horse").unwrap().secret(), keypair.secret());
+    assert!(keystore.unlock("wrong horse").is_err());
+
+    let mut tampered = keystore.clone();
+    tampered.address = Keypair::generate().unwrap().address().to_string();
+    assert!(tampered.unlock("correct horse").is_err());
+    assert!(Keystore::seal("../escape", &keypair, "pw").is_err());
+}
+
+#[test]
+fn keystore_refuses_argon2_parameters_out_of_range() {
+    let keystore = Keystore::seal("alice", &Keypair::generate().unwrap(), "pw").unwrap();
+    for (memory_kib, passes) in [(u32::MAX, 2), (0, 2), (19 * 1024, 0), (19 * 1024, u32::MAX)] {
+        let mut doctored = keystore.clone();
+        doctored.crypto.memory_kib = memory_kib;
+        doctored.crypto.passes = passes;
+        let error = doctored.unlock("pw").unwrap_err().to_string();
+        assert!(error.contains("outside"), "{} KiB, {} passes: {}", memory_kib, passes, error);
+    }
+}
+
+#[test]
+fn keystore_files_are_listed_and_never_overwritten() {
+    let dir = scratch_dir("wallets");
+    assert!(Keystore::list(&dir).unwrap().is_empty());
+    for name in ["bob", "alice"] {
+        Keystore::seal(name, &Keypair::generate().unwrap(), "pw").unwrap().save(&dir).unwrap();
+    }
+    let names: Vec<_> = Keystore::list(&dir).unwrap().into_iter().map(|w| w.name).collect();
+    assert_eq!(names, ["alice", "bob"]);
+
+    let alice = Keystore::load(&dir, "alice").unwrap().unwrap();
+    assert!(Keystore::seal("alice", &Keypair::generate().unwrap(), "pw").unwrap().save(&dir).is_err());
+    assert_eq!(Keystore::load(&dir, "alice").unwrap().unwrap(), alice);
+    assert!(Keystore::load(&dir, "carol").unwrap().is_none());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn derivation_matches_the_slip10_test_vectors() {
+    let seed: Vec<u8> = (0..16).collect();
+    let master = ExtendedKey::master(&seed);
+    assert_eq!(hex(&master.secret()), "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7");
+    assert_eq!(hex(&master.chain_code()), "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb");
+    assert_eq!(hex(&master.child(0).secret()), "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3");
+    assert_eq!(hex(&master.derive(&[0, 1, 2]).secret()), "92a5b23c0b8a99e37d07df3fb9966917f5d06e02ddbd909c7e184371463e9fc9");
+    assert_eq!(hd::path_string(0, 3), "m/44'/1'/0'/0'/3'");
+}
+
+#[test]
+fn mnemonics_round_trip_and_reject_typos() {
+    let mnemonic = Mnemonic::generate().unwrap();
+    assert_eq!(mnemonic.word_count(), 24);
+    let phrase = mnemonic.to_string();
+    assert_eq!(phrase.parse::<Mnemonic>(), Ok(mnemonic.clone()));
+    assert_eq!(format!("  {}\n", phrase.to_uppercase()).parse::<Mnemonic>(), Ok(mnemonic.clone()));
+    assert_eq!(Mnemonic::from_entropy(&mnemonic.entropy()), Some(mnemonic));
+
+    let zero = Mnemonic::from_entropy(&[0; 16]).unwrap();
+    assert_eq!(zero.to_string(), format!("{}about", "abandon ".repeat(11)));
+    assert!(format!("{}abandon", "abandon ".repeat(11)).parse::<Mnemonic>().is_err());
+    assert!("abandon".parse::<Mnemonic>().is_err());
+}
+
+#[test]
+fn hd_wallets_derive_unlock_and_restore() {
+    let mnemonic = Mnemonic::from_entropy(&[9; 32]).unwrap();
+    let mut keystore = Keystore::seal_hd("alice", &mnemonic, 0, 1, "pw").unwrap();
+    assert!(!serde_json::to_string(&keystore).unwrap().contains(&hex(&mnemonic.entropy())));
+    assert_eq!(keystore.mnemonic("pw").unwrap(), Some(mnemonic.clone()));
+    assert!(keystore.mnemonic("wrong").is_err());
+    let second = keystore.derive_next("pw").unwrap();
+    let keys = keystore.unlock_all("pw").unwrap();
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys[1].secret(), second.secret());
+    assert_eq!(keystore.addresses(), [keystore.address.clone(), second.address().to_string()]);
+    assert_eq!(keystore.unlock("pw").unwrap().address().to_string(), keystore.address);
+
+    // Restoring finds addresses up to the gap limit past the last one used.
+    let seed = mnemonic.seed();
+    let used = [hd::derive(&seed, 0, 2), hd::derive(&seed, 0, 2 + GAP_LIMIT)].map(|k| k.address().to_string());
+    let restored = Keystore::restore("alice", &mnemonic, 0, "pw", |a| used.iter().any(|u| u == a)).unwrap();
+    assert_eq!(restored.addresses().len() as u32, 3 + GAP_LIMIT);
+    assert_eq!(restored.addresses()[..2], keystore.addresses()[..]);
+    let too_far = [hd::derive(&seed, 0, GAP_LIMIT + 3).address().to_string()];
+    assert_eq!(Keystore::restore("alice", &mnemonic, 0, "pw", |a| too_far[0] == a).unwrap().addresses().len(), 1);
+
+    // Another account has other keys, and single-key wallets have no mnemonic.
+    assert_ne!(Keystore::seal_hd("alice", &mnemonic, 1, 1, "pw").unwrap().address, keystore.address);
+    let single = Keystore::seal("bob", &Keypair::from_secret([3; 32]), "pw").unwrap();
+    assert_eq!(single.mnemonic("pw").unwrap(), None);
+    assert!(single.clone().derive_next("pw").is_err());
+    assert_eq!(single.addresses(), [single.address.as_str()]);
+}